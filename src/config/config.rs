@@ -15,6 +15,27 @@ pub struct AppConfig {
     pub max_pages: Option<usize>,
     pub sitemap_max_depth: Option<usize>, // สำหรับ recursive sitemap loading
     pub max_sitemap_urls: Option<usize>, // จำกัดจำนวน URL จาก sitemap
+    pub sitemap_selection: Option<String>, // วิธีเลือก URL เมื่อเกิน max_sitemap_urls
+    pub concurrency: Option<usize>, // จำนวน URL ที่โหลดพร้อมกัน
+    pub requests_per_second: Option<f64>, // rate limit ต่อ host (ไม่ระบุ = คำนวณจาก delay_ms)
+    pub rate_limit_burst: Option<u32>, // จำนวน request ที่ยิงติดกันได้ต่อ host
+    pub max_crawl_delay_ms: Option<u64>, // Crawl-delay/Request-rate จาก robots.txt ที่ยอมทำตามได้สูงสุด
+    pub retry_max_attempts: Option<u32>, // จำนวนครั้งที่ลองโหลดทั้งหมด (รวมครั้งแรก)
+    pub retry_base_delay_ms: Option<u64>, // delay ของ retry ครั้งแรก (เพิ่มเป็น 2 เท่าทุกครั้ง)
+    pub retry_max_delay_ms: Option<u64>, // delay สูงสุดต่อครั้ง และ Retry-After ที่ยอมรอได้
+    pub skip_non_success: Option<bool>, // ข้ามหน้าที่ตอบ status ไม่ใช่ 2xx
+    pub soft_404_patterns: Option<Vec<String>>, // ข้อความใน title/h1 ที่ถือว่าเป็น soft-404
+    pub min_text_chars: Option<usize>, // ข้ามหน้าที่มีข้อความน้อยกว่านี้
+    pub max_redirects: Option<usize>, // จำนวน redirect hop สูงสุด
+    pub cross_host_redirects: Option<String>, // "same_site", "same_host" หรือ "any"
+    pub respect_canonical: Option<bool>, // ตัดหน้าซ้ำตาม <link rel="canonical">
+    pub tracking_params: Option<Vec<String>>, // query params ที่ตัดทิ้งตอน normalize URL ("utm_*" = ขึ้นต้นด้วย)
+    pub sort_query_params: Option<bool>, // เรียง query params ตอน normalize URL
+    pub skip_content_types: Option<Vec<String>>, // content type ที่ไม่แปลง ("image/*" = ทุก subtype)
+    pub extract_main_content: Option<bool>, // แปลงเฉพาะเนื้อหาหลัก (ตัดเมนู, header, footer, แถบข้าง)
+    pub table_fallback: Option<String>, // ตารางที่มี colspan/rowspan หรือ block ซ้อน: "html" หรือ "list"
+    pub internal_links: Option<String>, // link ไปหน้าที่ crawl แล้ว: "absolute" (URL เต็ม) หรือ "local" (ไฟล์ .md ที่บันทึก)
+    pub front_matter: Option<bool>, // เขียน YAML front matter (URL, title, canonical, lastmod, hash ฯลฯ) หัวไฟล์ markdown
 }
 
 impl Default for AppConfig {
@@ -29,6 +50,27 @@ impl Default for AppConfig {
             max_pages: Some(200),
             sitemap_max_depth: Some(5), // รองรับ sitemap ซ้อนได้ 5 ชั้น
             max_sitemap_urls: Some(100), // จำกัด URL จาก sitemap 100 URLs ตาม default
+            sitemap_selection: Some("sitemap_order".into()), // ตามลำดับในไฟล์ sitemap
+            concurrency: Some(4), // โหลดพร้อมกัน 4 URLs
+            requests_per_second: None, // ใช้ 1000 / delay_ms
+            rate_limit_burst: Some(1),
+            max_crawl_delay_ms: Some(60_000), // site ที่ขอนานกว่า 1 นาทีต่อ request ถูกลดเหลือ 1 นาที
+            retry_max_attempts: Some(3),
+            retry_base_delay_ms: Some(500),
+            retry_max_delay_ms: Some(30_000), // รอ Retry-After ได้ไม่เกิน 30 วินาที
+            skip_non_success: Some(true),
+            soft_404_patterns: None, // ใช้ค่า default ใน page_filter
+            min_text_chars: Some(1), // ข้ามเฉพาะหน้าที่ว่างเปล่า
+            max_redirects: Some(5),
+            cross_host_redirects: Some("same_site".into()), // ยอม host ที่ registrable domain เดียวกัน
+            respect_canonical: Some(true),
+            tracking_params: None, // ใช้ค่า default ใน canonical
+            sort_query_params: Some(true),
+            skip_content_types: None, // ใช้ค่า default ใน html_fetcher
+            extract_main_content: Some(false), // ตั้งเฉพาะโดเมนได้ใน whitelist.yaml
+            table_fallback: Some("html".into()),
+            internal_links: Some("absolute".into()),
+            front_matter: Some(false),
         }
     }
 }
@@ -0,0 +1,201 @@
+use scraper::Html;
+use spider::url::Url;
+
+use super::html_fetcher::FetchedPage;
+use crate::config::config::AppConfig;
+
+/// query param ที่ใช้ติดตามแคมเปญ ไม่มีผลกับเนื้อหา ("*" ท้ายชื่อ = ขึ้นต้นด้วย)
+const DEFAULT_TRACKING_PARAMS: &[&str] = &[
+    "utm_*", "fbclid", "gclid", "dclid", "msclkid", "yclid", "mc_cid", "mc_eid", "_ga", "_gl", "igshid",
+];
+
+/// ชื่อไฟล์ index ที่ server ส่งเนื้อหาเดียวกับ path ของ directory
+const INDEX_FILES: &[&str] = &["index.html", "index.htm", "index.php"];
+
+/// แปลง URL ให้อยู่ในรูปเดียวกันเพื่อใช้เป็น key ตอนตัดหน้าซ้ำ
+/// - ตัด fragment, ตัด tracking params, เรียง query params, host ตัวพิมพ์เล็ก
+/// - /index.html -> /
+#[derive(Debug, Clone)]
+pub struct UrlNormalizer {
+    pub tracking_params: Vec<String>,
+    pub sort_query: bool,
+}
+
+impl UrlNormalizer {
+    pub fn from_config(cfg: &AppConfig) -> Self {
+        let params = cfg
+            .tracking_params
+            .clone()
+            .unwrap_or_else(|| DEFAULT_TRACKING_PARAMS.iter().map(|p| p.to_string()).collect());
+        Self {
+            tracking_params: params.into_iter().map(|p| p.trim().to_ascii_lowercase()).collect(),
+            sort_query: cfg.sort_query_params.unwrap_or(true),
+        }
+    }
+
+    /// URL ที่ normalize แล้ว (parse ไม่ได้ -> คืนค่าเดิม)
+    pub fn normalize(&self, url: &str) -> String {
+        let Ok(mut parsed) = Url::parse(url.trim()) else {
+            return url.trim().to_string();
+        };
+        // Url::parse ทำ host เป็นตัวพิมพ์เล็กและตัด default port ให้แล้ว
+        parsed.set_fragment(None);
+
+        let path = parsed.path().to_string();
+        if let Some(dir) = INDEX_FILES.iter().find_map(|f| path.strip_suffix(f))
+            && dir.ends_with('/')
+        {
+            parsed.set_path(dir);
+        }
+
+        let mut pairs: Vec<(String, String)> = parsed
+            .query_pairs()
+            .filter(|(k, _)| !self.is_tracking(k))
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect();
+        if self.sort_query {
+            pairs.sort();
+        }
+        if pairs.is_empty() {
+            parsed.set_query(None);
+        } else {
+            parsed.query_pairs_mut().clear().extend_pairs(pairs);
+        }
+        parsed.to_string()
+    }
+
+    fn is_tracking(&self, name: &str) -> bool {
+        let name = name.to_ascii_lowercase();
+        self.tracking_params.iter().any(|p| match p.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == *p,
+        })
+    }
+}
+
+/// canonical URL ของหน้า จาก header `Link: <...>; rel="canonical"` หรือ `<link rel="canonical">`
+/// คืน URL แบบ absolute (resolve กับ final_url)
+pub fn canonical_url(page: &FetchedPage) -> Option<String> {
+    let base = Url::parse(&page.final_url).ok()?;
+    let href = page
+        .headers
+        .get_all("link")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .find_map(canonical_from_link_header)
+        .or_else(|| find_canonical_link(&page.html()))?;
+    base.join(href.trim()).ok().map(|u| u.to_string())
+}
+
+/// แยก `<https://example.com/a>; rel="canonical"` จาก Link header
+fn canonical_from_link_header(value: &str) -> Option<String> {
+    let mut parts = value.split(';');
+    let target = parts.next()?.trim().strip_prefix('<')?.strip_suffix('>')?;
+    let is_canonical = parts.any(|param| {
+        param
+            .split_once('=')
+            .is_some_and(|(name, value)| {
+                name.trim().eq_ignore_ascii_case("rel")
+                    && value.trim().trim_matches('"').split_whitespace().any(|r| r.eq_ignore_ascii_case("canonical"))
+            })
+    });
+    is_canonical.then(|| target.to_string())
+}
+
+/// หา href ของ `<link rel="canonical">` แรกในหน้า HTML
+fn find_canonical_link(html: &str) -> Option<String> {
+    Html::parse_document(html)
+        .root_element()
+        .descendent_elements()
+        .filter(|el| el.value().name() == "link")
+        .find_map(|el| {
+            let link = el.value();
+            let rel = link.attr("rel")?;
+            let href = link.attr("href").filter(|h| !h.trim().is_empty())?;
+            rel.split_whitespace()
+                .any(|r| r.eq_ignore_ascii_case("canonical"))
+                .then(|| href.to_string())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crawler::html_fetcher::FetchMode;
+    use spider::reqwest::header::{HeaderMap, HeaderValue};
+    use std::time::Duration;
+
+    fn normalizer() -> UrlNormalizer {
+        UrlNormalizer::from_config(&AppConfig::default())
+    }
+
+    fn page(url: &str, link_header: Option<&str>, html: &str) -> FetchedPage {
+        let mut response = spider::page::Page::default();
+        response.final_redirect_destination = Some(url.to_string());
+        let mut page = FetchedPage::from_spider_page(url, &response, FetchMode::HttpRequest, Duration::ZERO);
+        let mut headers = HeaderMap::new();
+        if let Some(value) = link_header {
+            headers.insert("link", HeaderValue::from_str(value).unwrap());
+        }
+        page.headers = headers;
+        page.bytes = html.as_bytes().to_vec();
+        page
+    }
+
+    #[test]
+    fn normalize_rules() {
+        let n = normalizer();
+        let cases = [
+            // tracking params: "utm_*" ตัดทุกตัวที่ขึ้นต้นด้วย utm_, ชื่ออื่นต้องตรงทั้งคำ
+            ("https://example.com/a?utm_source=x&UTM_Medium=y&utm=keep", "https://example.com/a?utm=keep"),
+            ("https://example.com/a?fbclid=1&fbclidx=2", "https://example.com/a?fbclidx=2"),
+            ("https://example.com/a?utm_source=x", "https://example.com/a"),
+            // เรียง query params ตามชื่อแล้วตามค่า
+            ("https://example.com/a?b=2&a=9&a=1", "https://example.com/a?a=1&a=9&b=2"),
+            // index file -> directory, แต่ไม่ตัดถ้าเป็นส่วนหนึ่งของชื่อไฟล์อื่น
+            ("https://example.com/docs/index.html", "https://example.com/docs/"),
+            ("https://example.com/index.php?b=1", "https://example.com/?b=1"),
+            ("https://example.com/myindex.html", "https://example.com/myindex.html"),
+            // fragment, host ตัวพิมพ์ใหญ่, default port
+            ("https://EXAMPLE.com:443/a#section", "https://example.com/a"),
+            ("  not a url  ", "not a url"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(n.normalize(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn normalize_keeps_query_order_when_sorting_is_off() {
+        let mut n = normalizer();
+        n.sort_query = false;
+        assert_eq!(n.normalize("https://example.com/a?b=2&a=1"), "https://example.com/a?b=2&a=1");
+    }
+
+    #[test]
+    fn link_header_wins_over_link_element() {
+        let html = r#"<html><head><link rel="canonical" href="/from-html"></head></html>"#;
+        let header = r#"<https://cdn.example.com/style.css>; rel="preload", </from-header>; rel="canonical""#;
+        let page = page("https://example.com/a/b", Some(header), html);
+        assert_eq!(canonical_url(&page).as_deref(), Some("https://example.com/from-header"));
+    }
+
+    #[test]
+    fn link_element_is_resolved_against_final_url() {
+        let html = r#"<html><head>
+            <link rel="stylesheet" href="/style.css">
+            <LINK REL="Canonical" HREF="../other">
+            <link rel="canonical" href="/second">
+        </head></html>"#;
+        let page = page("https://example.com/a/b", Some(r#"<https://example.com/x>; rel="next""#), html);
+        assert_eq!(canonical_url(&page).as_deref(), Some("https://example.com/other"));
+    }
+
+    #[test]
+    fn missing_or_empty_canonical() {
+        let html = r#"<html><head><link rel="canonical" href="  "></head></html>"#;
+        assert_eq!(canonical_url(&page("https://example.com/a", None, html)), None);
+        assert_eq!(canonical_url(&page("https://example.com/a", None, "<p>x</p>")), None);
+    }
+}
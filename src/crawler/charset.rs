@@ -0,0 +1,141 @@
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8, UTF_16BE, UTF_16LE};
+use spider::url::Url;
+
+/// จำนวน byte แรกที่ใช้หา `<meta charset>` (HTML spec กำหนด 1024 เผื่อหน้าที่ head ยาวไว้)
+const META_PRESCAN_BYTES: usize = 4096;
+
+/// ที่มาของ charset ที่ตรวจพบ
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CharsetSource {
+    /// byte order mark ต้นไฟล์
+    Bom,
+    /// parameter charset ของ Content-Type header
+    Header,
+    /// `<meta charset>` หรือ `<meta http-equiv="Content-Type">`
+    Meta,
+    /// ไม่มีการประกาศ เดาจากเนื้อหา (UTF-8 ถ้าถูกต้อง ไม่เช่นนั้นใช้ chardetng)
+    Detected,
+    /// Chrome ส่ง DOM ที่ decode แล้วเป็น UTF-8 เสมอ
+    Browser,
+}
+
+/// หา encoding ของ HTML ตามลำดับ BOM -> Content-Type header -> meta -> เดาจากเนื้อหา
+/// - header_charset: ค่า charset จาก Content-Type (ถ้ามี)
+/// - url: ใช้ TLD เป็น hint ตอนเดา (เช่น .th -> windows-874)
+pub fn detect_encoding(
+    bytes: &[u8],
+    header_charset: Option<&str>,
+    url: &str,
+) -> (&'static Encoding, CharsetSource) {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return (encoding, CharsetSource::Bom);
+    }
+    if let Some(encoding) = header_charset.and_then(|c| Encoding::for_label(c.trim().as_bytes())) {
+        return (encoding, CharsetSource::Header);
+    }
+    if let Some(encoding) = meta_charset(&bytes[..bytes.len().min(META_PRESCAN_BYTES)]) {
+        // HTML spec: meta ที่ประกาศ UTF-16 (แต่ไม่มี BOM) ให้ถือเป็น UTF-8
+        let encoding = if encoding == UTF_16LE || encoding == UTF_16BE {
+            UTF_8
+        } else {
+            encoding
+        };
+        return (encoding, CharsetSource::Meta);
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return (UTF_8, CharsetSource::Detected);
+    }
+
+    let tld = Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().and_then(|h| h.rsplit('.').next()).map(str::to_ascii_lowercase));
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    (detector.guess(tld.as_deref().map(str::as_bytes), true), CharsetSource::Detected)
+}
+
+/// แปลง bytes เป็น UTF-8 ด้วย encoding ที่ตรวจได้ (ตัด BOM ออก, byte ที่ผิดถูกแทนด้วย U+FFFD)
+pub fn decode(bytes: &[u8], encoding: &'static Encoding) -> String {
+    let (text, _, _) = encoding.decode(bytes);
+    text.into_owned()
+}
+
+/// หา charset จาก `<meta charset="...">` หรือ `<meta http-equiv="Content-Type" content="...; charset=...">`
+fn meta_charset(head: &[u8]) -> Option<&'static Encoding> {
+    // ส่วนที่ประกาศ charset เป็น ASCII เสมอ แปลงแบบ lossy ได้โดยไม่กระทบ
+    let lower = String::from_utf8_lossy(head).to_ascii_lowercase();
+    let mut pos = 0usize;
+    while let Some(rel) = lower[pos..].find("<meta") {
+        let start = pos + rel;
+        let end = start + lower[start..].find('>')?;
+        let tag = &lower[start..end];
+        pos = end;
+
+        let Some(idx) = tag.find("charset") else {
+            continue;
+        };
+        let value = tag[idx + "charset".len()..].trim_start();
+        let Some(value) = value.strip_prefix('=') else {
+            continue;
+        };
+        let label: String = value
+            .trim_start()
+            .trim_start_matches(['"', '\''])
+            .chars()
+            .take_while(|c| !c.is_ascii_whitespace() && !matches!(c, '"' | '\'' | ';' | '/' | '>'))
+            .collect();
+        if let Some(encoding) = Encoding::for_label(label.as_bytes()) {
+            return Some(encoding);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{SHIFT_JIS, WINDOWS_1252, WINDOWS_874};
+
+    #[test]
+    fn declaration_precedence() {
+        let meta_sjis = b"<html><head><meta charset=\"shift_jis\"></head><body>x</body></html>".to_vec();
+        let with_bom = [b"\xEF\xBB\xBF".as_slice(), &meta_sjis].concat();
+        let http_equiv = b"<meta http-equiv='Content-Type' content='text/html; charset=TIS-620'>".to_vec();
+        let cases: [(&[u8], Option<&str>, &Encoding, CharsetSource); 8] = [
+            (&with_bom, Some("iso-8859-1"), UTF_8, CharsetSource::Bom),
+            (b"\xFF\xFEx\x00", Some("utf-8"), UTF_16LE, CharsetSource::Bom),
+            (&meta_sjis, Some(" windows-1252 "), WINDOWS_1252, CharsetSource::Header),
+            (&meta_sjis, Some("bogus"), SHIFT_JIS, CharsetSource::Meta),
+            (&meta_sjis, None, SHIFT_JIS, CharsetSource::Meta),
+            (&http_equiv, None, WINDOWS_874, CharsetSource::Meta),
+            (b"<meta charset=utf-16>", None, UTF_8, CharsetSource::Meta),
+            (b"<p>plain ascii</p>", None, UTF_8, CharsetSource::Detected),
+        ];
+        for (bytes, header, encoding, source) in cases {
+            assert_eq!(
+                detect_encoding(bytes, header, "https://example.com/"),
+                (encoding, source),
+                "{:?}",
+                String::from_utf8_lossy(bytes)
+            );
+        }
+    }
+
+    #[test]
+    fn meta_after_prescan_limit_is_ignored() {
+        let mut bytes = format!("<html><head><!-- {} -->", "x".repeat(META_PRESCAN_BYTES)).into_bytes();
+        bytes.extend_from_slice(b"<meta charset=\"shift_jis\"></head></html>");
+        assert_eq!(detect_encoding(&bytes, None, "https://example.com/"), (UTF_8, CharsetSource::Detected));
+    }
+
+    #[test]
+    fn undeclared_tis620_page_is_detected_and_decoded() {
+        let text = "ภาษาไทยเป็นภาษาราชการของประเทศไทย มีผู้พูดหลายสิบล้านคนทั่วประเทศ และใช้อักษรไทยในการเขียน";
+        let html = format!("<html><body><p>{}</p></body></html>", text);
+        let (encoded, _, _) = WINDOWS_874.encode(&html);
+        let (encoding, source) = detect_encoding(&encoded, None, "https://www.example.co.th/");
+        assert_eq!((encoding, source), (WINDOWS_874, CharsetSource::Detected));
+        assert!(decode(&encoded, encoding).contains(text));
+    }
+}
@@ -2,33 +2,53 @@ use spider::compact_str::CompactString;
 use spider::features::chrome_common::RequestInterceptConfiguration;
 use spider::website::Website;
 use std::error::Error;
-use tokio::time::{sleep, Duration};
 
+use crate::crawler::html_fetcher::{page_result, FetchMode, FetchResult};
+use crate::crawler::rate_limiter::RateLimiter;
+use crate::crawler::redirect::RedirectRules;
+use crate::crawler::retry::{scrape_with_retry, RetryPolicy};
+
+/// โหลดหน้าด้วย Chrome (render JavaScript)
+/// Chrome follow redirect เอง (จำกัดจำนวน hop ด้วย max_redirects) จึงตรวจ cross_host_redirects
+/// และ robots.txt ของปลายทางได้หลังโหลดแล้วเท่านั้น (ใน crawler ก่อนแปลง/บันทึก) ต่างจาก HttpRequest ที่ตรวจก่อนทุก hop
+/// redirect_chain มีแค่ URL ที่ขอ (ไม่ทราบ hop และ status ระหว่างทาง)
 pub async fn fetch_with_chrome(
     urls: Vec<String>,
     user_agent: &str,
-    delay_ms: u64,
-) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    limiter: &RateLimiter,
+    retry: &RetryPolicy,
+    redirects: &RedirectRules,
+) -> Result<Vec<FetchResult>, Box<dyn Error>> {
     let mut results = Vec::new();
 
     for url in urls {
         println!("[chrome_fetcher] fetching {}", url);
 
-        let mut website = Website::new(&url);
-        website.configuration.user_agent = Some(Box::new(CompactString::from(user_agent)));
-        website.with_depth(0);
-        website.with_chrome_intercept(RequestInterceptConfiguration::default());
-
-        website.scrape().await;
-
-        if let Some(page) = website.get_pages().and_then(|p| p.first()) {
-            results.push((url.clone(), page.get_html().to_string()));
-        }
+        let t0 = std::time::Instant::now();
+        let page = scrape_with_retry(&url, limiter, retry, || {
+            let mut website = Website::new(&url);
+            website.configuration.user_agent = Some(Box::new(CompactString::from(user_agent)));
+            website.with_depth(0);
+            website.with_chrome_intercept(RequestInterceptConfiguration::default());
+            redirects.apply(&mut website);
+            website
+        })
+        .await;
 
-        if delay_ms > 0 {
-            sleep(Duration::from_millis(delay_ms)).await;
+        let result = page_result(&url, page, retry, FetchMode::Chrome, t0.elapsed()).map(|mut fetched| {
+            if fetched.final_url != fetched.url {
+                fetched.redirect_chain = vec![(0, url.clone())];
+            }
+            fetched
+        });
+        if let Err(failure) = &result {
+            eprintln!("[chrome_fetcher] failed {}", failure);
         }
+        results.push(result);
     }
-    println!("[chrome_fetcher] finished, got {} pages", results.len());
+    println!(
+        "[chrome_fetcher] finished, got {} pages",
+        results.iter().filter(|r| r.is_ok()).count()
+    );
     Ok(results)
 }
\ No newline at end of file
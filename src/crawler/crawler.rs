@@ -1,9 +1,22 @@
+use super::canonical::{canonical_url, UrlNormalizer};
 use super::domain_detector::DomainDetector;
-use super::html_fetcher::{fetch_html_from_urls, FetchMode};
-use super::html_to_markdown::html_to_markdown;
+use super::front_matter::FrontMatter;
+use super::html_fetcher::{fetch_html_from_urls, guess_content_type_from_url, ContentDispatcher, FetchFailure, FetchMode};
+use super::html_to_markdown::ConvertOptions;
+use super::link_rewriter::{InternalLinks, LinkMap};
 use super::markdown_writer::write_markdown_file;
-use super::robots::{crawl_with_spider, get_sitemaps_from_robots, fetch_sitemap_recursive};
-use std::collections::HashSet;
+use super::page_filter::{PageFilter, SkipReason};
+use super::rate_limiter::{RateLimit, RateLimiter};
+use super::robots::{crawl_with_spider, discover_feeds, fetch_robots_txt, fetch_sitemap_recursive, RobotsCache};
+use super::retry::RetryPolicy;
+use super::robots_txt::RobotsTxt;
+use super::sitemap::SitemapEntry;
+use super::sitemap_selection::{select_entries, SelectionStrategy};
+use futures::stream::{self, StreamExt};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 // use centralized config loader
 use crate::config::config::load_app_config;
@@ -18,9 +31,15 @@ pub async fn run_crawler(domain: &str) -> Result<(), Box<dyn std::error::Error>>
     // load app config (centralized)
     let cfg = load_app_config();
     let user_agent = cfg.user_agent.clone().unwrap_or_else(|| "MyRustCrawler/1.0".into());
-    let delay_ms = cfg.delay_ms.unwrap_or(250);
     let sitemap_max_depth = cfg.sitemap_max_depth.unwrap_or(5);
     let max_sitemap_urls = cfg.max_sitemap_urls.unwrap_or(100);
+    let concurrency = cfg.concurrency.unwrap_or(4).max(1);
+    let selection = SelectionStrategy::from_name(cfg.sitemap_selection.as_deref().unwrap_or("sitemap_order"));
+    let page_filter = PageFilter::from_config(&cfg);
+    let normalizer = UrlNormalizer::from_config(&cfg);
+    let respect_canonical = cfg.respect_canonical.unwrap_or(true);
+    let internal_links = InternalLinks::from_name(cfg.internal_links.as_deref().unwrap_or("absolute"));
+    let front_matter = cfg.front_matter.unwrap_or(false);
 
     // load whitelist detector (if available)
     let mut detector = DomainDetector::from_file(cfg.whitelist_path.as_deref().unwrap_or("src/config/whitelist.yaml"))
@@ -40,103 +59,132 @@ pub async fn run_crawler(domain: &str) -> Result<(), Box<dyn std::error::Error>>
         domain, chosen_mode, mode_name
     );
 
+    // HTML -> markdown เป็น default (PDF เมื่อเปิด feature "pdf"), handler อื่น register เพิ่มได้ที่นี่
+    let convert_options = ConvertOptions::from_config(&cfg, &detector, domain);
+    println!(
+        "[main_content] extract_main_content={}, content_selector={:?}",
+        convert_options.extract_main_content, convert_options.content_selector
+    );
+    let dispatcher = ContentDispatcher::from_config(&cfg, convert_options);
+
     // gather sitemap URLs
-    let mut sitemap_urls: Vec<String> = Vec::new();
+    let mut sitemap_entries: Vec<SitemapEntry> = Vec::new();
+
+    // rate limiter ต่อ host ใช้ร่วมกันทุก fetcher (robots.txt, sitemap, หน้าเว็บ)
+    // ค่า global จาก app.yaml, ค่าเฉพาะโดเมนจาก whitelist.yaml
+    let default_limit = RateLimit::from_config(&cfg);
+    let limiter = Arc::new(RateLimiter::new(default_limit, detector.rate_limits(default_limit)));
+    println!(
+        "[rate_limiter] default {:.3} req/s, burst {}",
+        default_limit.requests_per_second, default_limit.burst
+    );
 
-    match get_sitemaps_from_robots(domain).await {
+    // retry ใช้กับทุก fetch เช่นกัน (robots.txt, sitemap, หน้าเว็บ)
+    let retry = RetryPolicy::from_config(&cfg);
+    println!(
+        "[retry] max_attempts={}, base_delay={:?}, max_delay={:?}",
+        retry.max_attempts, retry.base_delay, retry.max_delay
+    );
+
+    let max_crawl_delay = Duration::from_millis(cfg.max_crawl_delay_ms.unwrap_or(60_000));
+    let robots_cache = RobotsCache::new(&user_agent, limiter.clone(), retry, max_crawl_delay);
+
+    // Crawl-delay/Request-rate ของ robots.txt ถูกส่งให้ limiter ตอน insert เข้า cache
+    let robots_result = fetch_robots_txt(domain, &user_agent, &limiter, &retry).await;
+    match robots_result {
+        Ok(ref robots) => robots_cache.insert(domain, robots.clone()),
+        // โหลดไม่ได้ -> ถือว่าห้ามทุก URL (RFC 9309) และไม่ต้องลองโหลดซ้ำ
+        Err(ref e) => {
+            eprintln!("[robots] โหลด robots.txt ของ {} ไม่ได้: {:?} -> ถือว่าห้ามทุก URL (RFC 9309)", domain, e);
+            robots_cache.insert(domain, RobotsTxt::disallow_all());
+        }
+    }
+
+    let robots_sitemaps = match robots_result.map(|r| r.sitemaps) {
         Ok(sitemaps) => {
-            println!("[log] get_sitemaps_from_robots returned {} entry(ies)", sitemaps.len());
+            println!("[log] fetch_robots_txt returned {} sitemap(s)", sitemaps.len());
             if sitemaps.is_empty() {
                 println!("-> ไม่พบ Sitemap URL ใน robots.txt");
-                
-                // ลอง /sitemap.xml ตรง ๆ แบบ recursive
-                let parsed = spider::url::Url::parse(domain)?;
-                let sitemap_url = parsed.join("/sitemap.xml")?.to_string();
-                let mut visited = HashSet::new();
-                
-                match fetch_sitemap_recursive(&sitemap_url, &user_agent, delay_ms, &mut visited, 0, sitemap_max_depth).await {
-                    Ok(recursive_sitemaps) => {
-                        println!(
-                            "[log] fetch_sitemap_recursive returned {} entry(ies)",
-                            recursive_sitemaps.len()
-                        );
-                        if recursive_sitemaps.is_empty() {
-                            let pretty = domain.trim_end_matches('/');
-                            println!("-> ไม่พบ sitemap.xml ที่ {}/sitemap.xml", pretty);
-                            // fallback to native spider crawl
-                            crawl_with_spider(domain).await?;
-                            return Ok(());
-                        } else {
-                            println!(
-                                "-> พบ {} URL(s) จาก sitemap recursive:",
-                                recursive_sitemaps.len()
-                            );
-                            sitemap_urls.extend(recursive_sitemaps);
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("-> ไม่สามารถโหลด sitemap.xml ได้: {:?}", e);
-                        return Err(e);
-                    }
-                }
-            } else {
-                println!("-> พบ {} Sitemap URL(s) จาก robots.txt:", sitemaps.len());
-                
-                // โหลดแต่ละ sitemap แบบ recursive
-                let mut visited = HashSet::new();
-                for sitemap_url in sitemaps {
-                    println!("   - กำลังโหลด sitemap: {}", sitemap_url);
-                    match fetch_sitemap_recursive(&sitemap_url, &user_agent, delay_ms, &mut visited, 0, sitemap_max_depth).await {
-                        Ok(urls) => {
-                            println!("     -> พบ {} URL(s)", urls.len());
-                            sitemap_urls.extend(urls);
-                        }
-                        Err(e) => {
-                            eprintln!("     -> ไม่สามารถโหลด {} ได้: {:?}", sitemap_url, e);
-                        }
-                    }
-                }
             }
+            sitemaps
         }
         Err(e) => {
             println!(
-                "[log] get_sitemaps_from_robots returned error: {:?}\n   -> ลองโหลด sitemap.xml ตรง ๆ แทน...",
+                "[log] fetch_robots_txt returned error: {:?}\n   -> ลองโหลด sitemap.xml ตรง ๆ แทน...",
                 e
             );
-            
-            // ลอง /sitemap.xml ตรง ๆ แบบ recursive
-            let parsed = spider::url::Url::parse(domain)?;
-            let sitemap_url = parsed.join("/sitemap.xml")?.to_string();
-            let mut visited = HashSet::new();
-            
-            match fetch_sitemap_recursive(&sitemap_url, &user_agent, delay_ms, &mut visited, 0, sitemap_max_depth).await {
-                Ok(sitemaps) => {
-                    println!("[log] fetch_sitemap_recursive returned {} entry(ies)", sitemaps.len());
-                    if sitemaps.is_empty() {
-                        let pretty = domain.trim_end_matches('/');
-                        println!("-> ไม่พบ sitemap.xml ที่ {}/sitemap.xml", pretty);
-                        crawl_with_spider(domain).await?;
-                        return Ok(());
-                    } else {
-                        println!("-> พบ {} URL(s) จาก sitemap recursive:", sitemaps.len());
-                        sitemap_urls.extend(sitemaps);
-                    }
+            Vec::new()
+        }
+    };
+
+    if robots_sitemaps.is_empty() {
+        let entries = discover_fallback_entries(domain, &user_agent, &limiter, &retry, &robots_cache, sitemap_max_depth).await?;
+        if entries.is_empty() {
+            // fallback to native spider crawl (ไม่ crawl ถ้า robots.txt ห้ามหน้าแรก หรือโหลด robots.txt ไม่ได้)
+            if !robots_cache.is_allowed(domain).await {
+                println!("-> robots.txt ไม่อนุญาต {} -> ไม่เริ่ม native spider crawl", domain);
+                return Ok(());
+            }
+            let mut written = LinkMap::new(normalizer.clone());
+            crawl_with_spider(domain, &cfg, &limiter, &retry, &page_filter, &dispatcher, &mut written).await?;
+            rewrite_internal_links(&written, internal_links);
+            return Ok(());
+        }
+        sitemap_entries.extend(entries);
+    } else {
+        println!("-> พบ {} Sitemap URL(s) จาก robots.txt:", robots_sitemaps.len());
+
+        // โหลดแต่ละ sitemap แบบ recursive
+        let mut visited = HashSet::new();
+        for sitemap_url in robots_sitemaps {
+            println!("   - กำลังโหลด sitemap: {}", sitemap_url);
+            match fetch_sitemap_recursive(&sitemap_url, &user_agent, &limiter, &retry, &mut visited, 0, sitemap_max_depth).await {
+                Ok(entries) => {
+                    println!("     -> พบ {} URL(s)", entries.len());
+                    sitemap_entries.extend(entries);
                 }
-                Err(e2) => {
-                    eprintln!("-> ไม่สามารถโหลด sitemap.xml ได้: {:?}", e2);
-                    return Err(e2);
+                Err(e) => {
+                    eprintln!("     -> ไม่สามารถโหลด {} ได้: {:?}", sitemap_url, e);
                 }
             }
         }
     }
 
+    // ตัด URL ที่ซ้ำกันหลัง normalize (tracking params, fragment, ลำดับ query) ก่อนโหลด
+    let before_dedup = sitemap_entries.len();
+    let mut seen_locs = HashSet::new();
+    sitemap_entries.retain(|entry| seen_locs.insert(normalizer.normalize(&entry.loc)));
+    if sitemap_entries.len() < before_dedup {
+        println!("-> ตัด URL ซ้ำใน sitemap {} URL(s)", before_dedup - sitemap_entries.len());
+    }
+
+    // ตรวจ robots.txt ของแต่ละ host ก่อนโหลด URL ใด ๆ
+    let mut allowed_entries = Vec::with_capacity(sitemap_entries.len());
+    let mut skipped_by_robots = Vec::new();
+    for entry in sitemap_entries {
+        if robots_cache.is_allowed(&entry.loc).await {
+            allowed_entries.push(entry);
+        } else {
+            println!("⛔ ข้ามตาม robots.txt: {}", entry.loc);
+            skipped_by_robots.push(entry.loc);
+        }
+    }
+    if !skipped_by_robots.is_empty() {
+        println!(
+            "-> ข้าม {} URL(s) ที่ robots.txt ไม่อนุญาตสำหรับ user_agent={}",
+            skipped_by_robots.len(),
+            user_agent
+        );
+    }
+    let mut sitemap_entries = allowed_entries;
+
     // If we have sitemap URLs -> fetch HTML using chosen fetch mode
-    if !sitemap_urls.is_empty() {
-        // Apply URL limit from config
-        if sitemap_urls.len() > max_sitemap_urls {
-            println!("-> จำกัดจำนวน URL จาก {} เป็น {} URLs (ตาม config max_sitemap_urls)", 
-                sitemap_urls.len(), max_sitemap_urls);
-            sitemap_urls.truncate(max_sitemap_urls);
+    if !sitemap_entries.is_empty() {
+        // Apply URL limit from config (เลือก URL ตาม sitemap_selection ก่อนตัด)
+        if sitemap_entries.len() > max_sitemap_urls {
+            println!("-> จำกัดจำนวน URL จาก {} เป็น {} URLs (ตาม config max_sitemap_urls, selection={:?})", 
+                sitemap_entries.len(), max_sitemap_urls, selection);
+            sitemap_entries = select_entries(sitemap_entries, selection, max_sitemap_urls);
         }
 
         let mode_str = match chosen_mode {
@@ -144,38 +192,254 @@ pub async fn run_crawler(domain: &str) -> Result<(), Box<dyn std::error::Error>>
             FetchMode::HttpRequest => "SSR (HttpRequest)",
         };
         println!(
-            "\n--- เริ่มโหลด HTML จาก {} sitemap URLs (mode: {}) ---",
-            sitemap_urls.len(),
-            mode_str
+            "\n--- เริ่มโหลด HTML จาก {} sitemap URLs (mode: {}, concurrency: {}) ---",
+            sitemap_entries.len(),
+            mode_str,
+            concurrency
         );
-        
-        // Process URLs one by one: download -> convert -> save immediately
-        let total = sitemap_urls.len() as f64;
-        for (idx, url) in sitemap_urls.iter().enumerate() {
-            let current = idx + 1;
-            let percent = if total > 0.0 { (current as f64 / total) * 100.0 } else { 0.0 };
-            println!("\n[{}/{}] ({:.1}%) กำลังดาวน์โหลด: {}", current, sitemap_urls.len(), percent, url);
-
-            // Fetch single URL
-            let html_results = fetch_html_from_urls(vec![url.clone()], chosen_mode, &user_agent, delay_ms).await?;
-
-            // Process result immediately
-            if let Some((fetched_url, html)) = html_results.into_iter().next() {
-                println!("✓ ดาวน์โหลดแล้ว: {} ({} bytes) — {:.1}%", fetched_url, html.len(), percent);
-
-                // Convert to markdown
-                let markdown = html_to_markdown(&fetched_url, &html);
-
-                // Save immediately
-                match write_markdown_file(&fetched_url, &markdown) {
-                    Ok(path) => println!("✓ บันทึกแล้ว: {} — {:.1}%", path.display(), percent),
-                    Err(err) => eprintln!("✗ บันทึกไม่สำเร็จ {}: {:?} — {:.1}%", fetched_url, err, percent),
+
+        // Worker pool: แต่ละ URL ผ่าน download -> convert -> save ทันที
+        // โดยมี URL ที่กำลังทำงานพร้อมกันไม่เกิน concurrency
+        // (ระยะห่างของ request ต่อ host คุมโดย rate limiter ภายใน fetcher)
+        let total = sitemap_entries.len();
+        let completed = AtomicUsize::new(0);
+        let failed = AtomicUsize::new(0);
+        let failures: Mutex<Vec<FetchFailure>> = Mutex::new(Vec::new());
+        let skipped: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+        // URL ปลายทาง (หลัง redirect) และ canonical ที่ normalize แล้ว -> URL จาก sitemap ที่บันทึกไปก่อน
+        // ใช้ตัดหน้าที่ redirect ไปที่เดียวกันหรือประกาศ canonical เดียวกัน
+        let seen_targets: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+        // ไฟล์ที่บันทึกแล้ว ใช้เปลี่ยน link เป็นไฟล์ .md หลังโหลดครบ (internal_links = local)
+        let written = Mutex::new(LinkMap::new(normalizer.clone()));
+
+        stream::iter(sitemap_entries.into_iter().enumerate())
+            .for_each_concurrent(concurrency, |(idx, entry)| {
+                let limiter = &limiter;
+                let completed = &completed;
+                let failed = &failed;
+                let failures = &failures;
+                let skipped = &skipped;
+                let page_filter = &page_filter;
+                let seen_targets = &seen_targets;
+                let written = &written;
+                let robots_cache = &robots_cache;
+                let normalizer = &normalizer;
+                let dispatcher = &dispatcher;
+                let retry = &retry;
+                let user_agent = &user_agent;
+                async move {
+                    let url = &entry.loc;
+                    // ไฟล์ที่รู้ชนิดจากนามสกุลและไม่มี handler รองรับ (เช่นรูป, วิดีโอ) ข้ามได้โดยไม่ต้องโหลด
+                    if let Some(content_type) = guess_content_type_from_url(url)
+                        && dispatcher.handler_for(content_type).is_none()
+                    {
+                        let done = completed.fetch_add(1, Ordering::SeqCst) + 1;
+                        let reason = SkipReason::ContentType(content_type.to_string());
+                        println!("⏭ [{}/{}] ข้าม {}: {}", done, total, url, reason);
+                        skipped
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .push((url.clone(), reason.to_string()));
+                        return;
+                    }
+
+                    println!("\n[#{}/{}] กำลังดาวน์โหลด: {}", idx + 1, total, url);
+
+                    // Fetch single URL (retry ภายใน fetcher)
+                    let result = match fetch_html_from_urls(vec![url.clone()], chosen_mode, user_agent, limiter, retry, &page_filter.redirects, robots_cache).await {
+                        Ok(results) => results.into_iter().next(),
+                        Err(e) => Some(Err(FetchFailure {
+                            url: url.clone(),
+                            reason: format!("{:?}", e),
+                        })),
+                    };
+
+                    let done = completed.fetch_add(1, Ordering::SeqCst) + 1;
+                    let percent = (done as f64 / total as f64) * 100.0;
+
+                    // Process result immediately
+                    match result {
+                        Some(Ok(page)) => {
+                            // HttpRequest ตรวจทุก hop ก่อน follow แล้ว แต่ Chrome ให้ spider follow redirect เอง
+                            // จึงต้องตรวจปลายทางของ redirect อีกครั้ง
+                            let final_allowed =
+                                page.final_url == page.url || robots_cache.is_allowed(&page.final_url).await;
+                            let handler = dispatcher.handler_for(&page.content_type);
+                            let reason = page_filter
+                                .check(&page)
+                                .or_else(|| (!final_allowed).then(|| SkipReason::RobotsDisallowed(page.final_url.clone())))
+                                .or_else(|| handler.is_none().then(|| SkipReason::ContentType(page.content_type.clone())))
+                                .or_else(|| {
+                                let mut keys = vec![normalizer.normalize(&page.final_url)];
+                                if respect_canonical && let Some(canonical) = canonical_url(&page) {
+                                    keys.push(normalizer.normalize(&canonical));
+                                }
+                                let mut seen = seen_targets.lock().unwrap_or_else(|e| e.into_inner());
+                                if let Some(first) = keys.iter().find_map(|k| seen.get(k)) {
+                                    return Some(SkipReason::Duplicate(first.clone()));
+                                }
+                                for key in keys {
+                                    seen.insert(key, page.url.clone());
+                                }
+                                None
+                            });
+                            let (None, Some(handler)) = (reason.as_ref(), handler) else {
+                                if let Some(SkipReason::Duplicate(first)) = &reason {
+                                    let mut written = written.lock().unwrap_or_else(|e| e.into_inner());
+                                    written.alias(&page.url, first);
+                                    written.alias(&page.final_url, first);
+                                }
+                                let reason = reason.map(|r| r.to_string()).unwrap_or_default();
+                                println!("⏭ [{}/{}] ข้าม {}: {} — {:.1}%", done, total, page.url, reason, percent);
+                                skipped
+                                    .lock()
+                                    .unwrap_or_else(|e| e.into_inner())
+                                    .push((page.url, reason));
+                                return;
+                            };
+                            if page.final_url != page.url {
+                                println!("   -> redirect: {}", page.describe_redirects());
+                            }
+                            println!(
+                                "✓ [{}/{}] ดาวน์โหลดแล้ว: {} (HTTP {}, {}, {} bytes, {:?}) — {:.1}%",
+                                done, total, page.url, page.status, page.content_type, page.bytes.len(), page.duration, percent
+                            );
+
+                            // Convert to markdown ด้วย handler ตาม content type
+                            let markdown = match handler.to_markdown(&page) {
+                                Ok(markdown) => markdown,
+                                Err(err) => {
+                                    failed.fetch_add(1, Ordering::SeqCst);
+                                    eprintln!("✗ [{}/{}] แปลง {} ด้วย {} ไม่สำเร็จ: {:?} — {:.1}%", done, total, page.url, handler.name(), err, percent);
+                                    return;
+                                }
+                            };
+
+                            // Save immediately (พร้อม front matter ถ้าเปิดไว้)
+                            let meta = front_matter.then(|| FrontMatter::from_page(&page, entry.lastmod.as_deref()));
+                            let path = written.lock().unwrap_or_else(|e| e.into_inner()).reserve(&page);
+                            match write_markdown_file(&path, &markdown, meta.as_ref()) {
+                                Ok(()) => {
+                                    println!("✓ [{}/{}] บันทึกแล้ว: {} — {:.1}%", done, total, path.display(), percent);
+                                    written.lock().unwrap_or_else(|e| e.into_inner()).record(&page, &path);
+                                }
+                                Err(err) => {
+                                    failed.fetch_add(1, Ordering::SeqCst);
+                                    eprintln!("✗ [{}/{}] บันทึกไม่สำเร็จ {}: {:?} — {:.1}%", done, total, page.url, err, percent)
+                                }
+                            }
+                        }
+                        Some(Err(failure)) => {
+                            failed.fetch_add(1, Ordering::SeqCst);
+                            eprintln!("✗ [{}/{}] ดาวน์โหลดไม่สำเร็จ: {} — {:.1}%", done, total, failure, percent);
+                            failures.lock().unwrap_or_else(|e| e.into_inner()).push(failure);
+                        }
+                        None => {
+                            failed.fetch_add(1, Ordering::SeqCst);
+                            eprintln!("✗ [{}/{}] ดาวน์โหลดไม่สำเร็จ: {} — {:.1}%", done, total, url, percent);
+                        }
+                    }
                 }
-            } else {
-                eprintln!("✗ ดาวน์โหลดไม่สำเร็จ: {} — {:.1}%", url, percent);
+            })
+            .await;
+
+        let skipped = skipped.into_inner().unwrap_or_else(|e| e.into_inner());
+        println!(
+            "\n--- เสร็จสิ้น: {}/{} URL(s) สำเร็จ, {} ข้าม, {} ไม่สำเร็จ ---",
+            total - failed.load(Ordering::SeqCst) - skipped.len(),
+            total,
+            skipped.len(),
+            failed.load(Ordering::SeqCst)
+        );
+
+        if !skipped.is_empty() {
+            println!("URL ที่ข้าม (error page, soft-404, เนื้อหาว่าง, redirect หรือซ้ำ):");
+            for (url, reason) in &skipped {
+                println!("   - {}: {}", url, reason);
             }
         }
+
+        let failures = failures.into_inner().unwrap_or_else(|e| e.into_inner());
+        if !failures.is_empty() {
+            println!("URL ที่โหลดไม่สำเร็จ (เหตุผลสุดท้ายหลัง retry):");
+            for failure in &failures {
+                println!("   - {}", failure);
+            }
+        }
+
+        rewrite_internal_links(&written.into_inner().unwrap_or_else(|e| e.into_inner()), internal_links);
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// internal_links = local: เปลี่ยน link ไปหน้าที่บันทึกแล้วในทุกไฟล์ให้ชี้ไฟล์ .md (เปิดดู offline ได้)
+fn rewrite_internal_links(written: &LinkMap, mode: InternalLinks) {
+    if mode != InternalLinks::Local || written.is_empty() {
+        return;
+    }
+    match written.rewrite_all() {
+        Ok(count) => println!("[link_rewriter] เปลี่ยน link เป็นไฟล์ .md แล้ว {} link(s)", count),
+        Err(e) => eprintln!("[link_rewriter] เขียน link ใหม่ไม่สำเร็จ: {:?}", e),
+    }
+}
+
+/// ใช้เมื่อ robots.txt ไม่มี Sitemap: ลอง /sitemap.xml ตรง ๆ แบบ recursive
+/// ถ้าไม่เจอ ลองหา RSS/Atom feed จากหน้าแรก (ถ้า robots.txt อนุญาต)
+/// คืน Vec ว่างถ้าไม่พบแหล่ง URL ใดเลย (ให้ caller fallback ไป spider crawl)
+async fn discover_fallback_entries(
+    domain: &str,
+    user_agent: &str,
+    limiter: &RateLimiter,
+    retry: &RetryPolicy,
+    robots_cache: &RobotsCache,
+    sitemap_max_depth: usize,
+) -> Result<Vec<SitemapEntry>, Box<dyn std::error::Error>> {
+    let parsed = spider::url::Url::parse(domain)?;
+    let sitemap_url = parsed.join("/sitemap.xml")?.to_string();
+    let mut visited = HashSet::new();
+
+    let entries = match fetch_sitemap_recursive(&sitemap_url, user_agent, limiter, retry, &mut visited, 0, sitemap_max_depth).await {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("-> ไม่สามารถโหลด sitemap.xml ได้: {:?}", e);
+            return Err(e);
+        }
+    };
+    println!("[log] fetch_sitemap_recursive returned {} entry(ies)", entries.len());
+    if !entries.is_empty() {
+        println!("-> พบ {} URL(s) จาก sitemap recursive:", entries.len());
+        return Ok(entries);
+    }
+
+    let pretty = domain.trim_end_matches('/');
+    println!("-> ไม่พบ sitemap.xml ที่ {}/sitemap.xml", pretty);
+
+    // ลอง RSS/Atom feed ที่ประกาศไว้ในหน้าแรก
+    if !robots_cache.is_allowed(domain).await {
+        println!("-> robots.txt ไม่อนุญาต {} -> ไม่โหลดหน้าแรกเพื่อหา feed", domain);
+        return Ok(Vec::new());
+    }
+    let feeds = match discover_feeds(domain, user_agent, limiter, retry).await {
+        Ok(feeds) => feeds,
+        Err(e) => {
+            eprintln!("-> ไม่สามารถโหลดหน้าแรกเพื่อหา feed ได้: {:?}", e);
+            Vec::new()
+        }
+    };
+
+    let mut feed_entries = Vec::new();
+    for feed_url in feeds {
+        match fetch_sitemap_recursive(&feed_url, user_agent, limiter, retry, &mut visited, 0, sitemap_max_depth).await {
+            Ok(entries) => {
+                println!("-> พบ {} URL(s) จาก feed {}", entries.len(), feed_url);
+                feed_entries.extend(entries);
+            }
+            Err(e) => eprintln!("-> ไม่สามารถโหลด feed {} ได้: {:?}", feed_url, e),
+        }
+    }
+    if feed_entries.is_empty() {
+        println!("-> ไม่พบ RSS/Atom feed ที่ใช้ได้");
+    }
+    Ok(feed_entries)
+}
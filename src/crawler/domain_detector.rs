@@ -5,12 +5,17 @@ use std::fs;
 use std::path::Path;
 
 use super::html_fetcher::FetchMode;
+use super::rate_limiter::{DomainRateLimit, RateLimit};
 
 #[derive(Debug, Deserialize, Clone)]
 struct WhitelistEntry {
     domain: String,
     mode: String,
     r#match: String, // Use raw identifier to avoid keyword conflict with 'match'
+    requests_per_second: Option<f64>, // rate limit เฉพาะโดเมน (ไม่ระบุ = ใช้ค่า global)
+    burst: Option<u32>,
+    extract_main_content: Option<bool>, // แปลงเฉพาะเนื้อหาหลัก (ไม่ระบุ = ใช้ค่า global)
+    content_selector: Option<String>, // CSS selector ของกล่องเนื้อหาหลัก
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -72,26 +77,53 @@ impl DomainDetector {
             return *m;
         }
 
-        for entry in &self.whitelist.whitelist {
-            let matches = match entry.r#match.as_str() {
-                "exact" => normalized == entry.domain,
-                "subdomain" => normalized == entry.domain || normalized.ends_with(&format!(".{}", entry.domain)),
-                _ => false,
-            };
-            if matches {
-                let mode = match entry.mode.as_str() {
-                    "SPA" => FetchMode::Chrome,
-                    "SSR" => FetchMode::HttpRequest,
-                    _ => FetchMode::from_str(&self.whitelist.default_mode),
-                };
-                self.cache.insert(normalized, mode);
-                return mode;
-            }
-        }
-
-        // Default case: no whitelist match, use default mode (fixes the missing return path)
-        let mode = FetchMode::from_str(&self.whitelist.default_mode);
+        let mode = match self.entry_for_domain(&normalized).map(|e| e.mode.as_str()) {
+            Some("SPA") => FetchMode::Chrome,
+            Some("SSR") => FetchMode::HttpRequest,
+            // ไม่อยู่ใน whitelist หรือ mode ไม่รู้จัก ใช้ default_mode
+            _ => FetchMode::from_str(&self.whitelist.default_mode),
+        };
         self.cache.insert(normalized, mode);
         mode
     }
-}
\ No newline at end of file
+
+    /// entry แรกใน whitelist ที่ตรงกับโดเมน
+    fn entry_for_domain(&self, domain: &str) -> Option<&WhitelistEntry> {
+        let normalized = Self::normalize_domain(domain);
+        self.whitelist.whitelist.iter().find(|entry| match entry.r#match.as_str() {
+            "exact" => normalized == entry.domain,
+            "subdomain" => normalized == entry.domain || normalized.ends_with(&format!(".{}", entry.domain)),
+            _ => false,
+        })
+    }
+
+    /// ค่า extract_main_content เฉพาะโดเมน (None = ใช้ค่าจาก app.yaml)
+    pub fn extract_main_content(&self, domain: &str) -> Option<bool> {
+        self.entry_for_domain(domain).and_then(|e| e.extract_main_content)
+    }
+
+    /// CSS selector ของเนื้อหาหลักเฉพาะโดเมน
+    pub fn content_selector(&self, domain: &str) -> Option<String> {
+        self.entry_for_domain(domain)
+            .and_then(|e| e.content_selector.clone())
+            .filter(|s| !s.trim().is_empty())
+    }
+
+    /// rate limit เฉพาะโดเมนจาก whitelist (เฉพาะ entry ที่ระบุ requests_per_second หรือ burst)
+    /// field ที่ไม่ได้ระบุใช้ค่าจาก default
+    pub fn rate_limits(&self, default: RateLimit) -> Vec<DomainRateLimit> {
+        self.whitelist
+            .whitelist
+            .iter()
+            .filter(|e| e.requests_per_second.is_some() || e.burst.is_some())
+            .map(|e| DomainRateLimit {
+                domain: Self::normalize_domain(&e.domain),
+                include_subdomains: e.r#match == "subdomain",
+                limit: RateLimit {
+                    requests_per_second: e.requests_per_second.unwrap_or(default.requests_per_second),
+                    burst: e.burst.unwrap_or(default.burst).max(1),
+                },
+            })
+            .collect()
+    }
+}
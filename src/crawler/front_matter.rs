@@ -0,0 +1,230 @@
+use chrono::SecondsFormat;
+use scraper::{ElementRef, Html};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::error::Error;
+
+use super::canonical::canonical_url;
+use super::html_fetcher::FetchedPage;
+
+const CONTENT_HASH_KEY: &str = "content_hash:";
+
+/// metadata ของหน้าที่เขียนเป็น YAML front matter ไว้หัวไฟล์ markdown (front_matter: true ใน app.yaml)
+/// field ที่หาไม่พบ (เช่น PDF ไม่มี <title>, URL ที่ไม่ได้มาจาก sitemap ไม่มี lastmod) จะไม่ถูกเขียน
+#[derive(Debug, Clone, Serialize)]
+pub struct FrontMatter {
+    pub source_url: String,
+    pub final_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canonical: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lastmod: Option<String>,
+    /// RFC 3339 (UTC)
+    pub fetched_at: String,
+    /// "HttpRequest" หรือ "Chrome" (ชื่อเดียวกับ fetch_mode ใน app.yaml)
+    pub fetch_mode: String,
+}
+
+/// front matter ที่เขียนจริง = metadata + hash ของ markdown ที่ตามมา
+#[derive(Serialize)]
+struct Header<'a> {
+    #[serde(flatten)]
+    meta: &'a FrontMatter,
+    content_hash: String,
+}
+
+impl FrontMatter {
+    /// เก็บ metadata จาก response/HTML ของหน้า และ lastmod จาก sitemap (ถ้ามี)
+    pub fn from_page(page: &FetchedPage, lastmod: Option<&str>) -> Self {
+        let (title, description, lang) = if page.is_html() {
+            html_metadata(&Html::parse_document(&page.html()))
+        } else {
+            (None, None, None)
+        };
+        // ไม่มี <html lang> ใช้ header Content-Language แทน (ค่าแรกถ้ามีหลายภาษา)
+        let lang = lang.or_else(|| {
+            let header = page.headers.get("content-language")?.to_str().ok()?;
+            non_empty(header.split(',').next()?)
+        });
+
+        Self {
+            source_url: page.url.clone(),
+            final_url: page.final_url.clone(),
+            title,
+            description,
+            canonical: canonical_url(page),
+            lang,
+            lastmod: lastmod.and_then(non_empty),
+            fetched_at: page.fetched_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            fetch_mode: format!("{:?}", page.mode),
+        }
+    }
+
+    /// markdown ที่มี front matter (คั่นด้วย `---`) นำหน้า
+    pub fn prepend(&self, markdown: &str) -> Result<String, Box<dyn Error>> {
+        let header = Header {
+            meta: self,
+            content_hash: content_hash(markdown),
+        };
+        Ok(format!("---\n{}---\n\n{}", serde_yaml::to_string(&header)?, markdown))
+    }
+}
+
+/// แยกไฟล์เป็น (front matter รวมบรรทัด `---` และบรรทัดว่างที่ตามมา, เนื้อหา markdown)
+pub fn split_front_matter(markdown: &str) -> (Option<&str>, &str) {
+    if !markdown.starts_with("---\n") {
+        return (None, markdown);
+    }
+    let Some(close) = markdown[3..].find("\n---\n") else {
+        return (None, markdown);
+    };
+    let mut end = 3 + close + "\n---\n".len();
+    if markdown[end..].starts_with('\n') {
+        end += 1;
+    }
+    (Some(&markdown[..end]), &markdown[end..])
+}
+
+/// front matter ที่ content_hash ตรงกับ markdown ใหม่ (เช่นหลังเปลี่ยน link เป็นไฟล์ .md)
+pub fn update_content_hash(front: &str, markdown: &str) -> String {
+    front
+        .split_inclusive('\n')
+        .map(|line| {
+            if line.starts_with(CONTENT_HASH_KEY) {
+                format!("{} {}\n", CONTENT_HASH_KEY, content_hash(markdown))
+            } else {
+                line.to_string()
+            }
+        })
+        .collect()
+}
+
+/// "sha256:<hex>" ของเนื้อหา markdown (ไม่รวม front matter) ใช้ตรวจว่าเนื้อหาเปลี่ยนตั้งแต่ ingest ครั้งก่อนหรือไม่
+fn content_hash(markdown: &str) -> String {
+    let digest = Sha256::digest(markdown.as_bytes());
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256:{}", hex)
+}
+
+/// (<title>, meta description, <html lang>)
+fn html_metadata(document: &Html) -> (Option<String>, Option<String>, Option<String>) {
+    let root = document.root_element();
+    let title = root
+        .descendent_elements()
+        .find(|el| el.value().name() == "title")
+        .and_then(|el| non_empty(&el.text().collect::<String>()));
+    let description = meta_content(root, "description").or_else(|| meta_content(root, "og:description"));
+    let lang = root
+        .value()
+        .attr("lang")
+        .or_else(|| root.value().attr("xml:lang"))
+        .and_then(non_empty);
+    (title, description, lang)
+}
+
+/// content ของ <meta name="..."> หรือ <meta property="..."> (ชื่อไม่สนตัวพิมพ์)
+fn meta_content(root: ElementRef, name: &str) -> Option<String> {
+    root.descendent_elements()
+        .filter(|el| el.value().name() == "meta")
+        .find(|el| {
+            let meta = el.value();
+            meta.attr("name")
+                .or_else(|| meta.attr("property"))
+                .is_some_and(|n| n.trim().eq_ignore_ascii_case(name))
+        })
+        .and_then(|el| non_empty(el.value().attr("content")?))
+}
+
+/// ยุบ whitespace (title/description ที่ขึ้นบรรทัดใหม่ใน HTML) คืน None ถ้าว่าง
+fn non_empty(text: &str) -> Option<String> {
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!collapsed.is_empty()).then_some(collapsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crawler::html_fetcher::FetchMode;
+    use spider::reqwest::header::HeaderValue;
+    use std::time::Duration;
+
+    fn page(html: &str) -> FetchedPage {
+        let url = "https://example.com/a";
+        let mut page = FetchedPage::from_spider_page(url, &spider::page::Page::default(), FetchMode::HttpRequest, Duration::ZERO);
+        page.final_url = "https://example.com/a/".to_string();
+        page.content_type = "text/html".to_string();
+        page.charset = "utf-8".to_string();
+        page.headers.insert("content-language", HeaderValue::from_static("th, en"));
+        page.bytes = html.as_bytes().to_vec();
+        page
+    }
+
+    fn yaml(front: &str) -> serde_yaml::Mapping {
+        let inner = front.trim_end().strip_prefix("---\n").unwrap().strip_suffix("---").unwrap();
+        serde_yaml::from_str(inner).unwrap()
+    }
+
+    #[test]
+    fn from_page_escapes_yaml_special_characters() {
+        let html = r#"<html><head>
+            <title>Rust: "Fearless"
+                Concurrency #1</title>
+            <meta name="Description" content="- starts like a list: 'quoted' &amp; more">
+            <link rel="canonical" href="/canonical">
+        </head><body><p>x</p></body></html>"#;
+        let meta = FrontMatter::from_page(&page(html), Some(" 2024-01-02 "));
+        let file = meta.prepend("# Body\n").unwrap();
+        let (front, body) = split_front_matter(&file);
+        assert_eq!(body, "# Body\n");
+
+        let parsed = yaml(front.unwrap());
+        let field = |key: &str| parsed.get(key).and_then(|v| v.as_str()).map(str::to_string);
+        assert_eq!(field("title").as_deref(), Some(r#"Rust: "Fearless" Concurrency #1"#));
+        assert_eq!(field("description").as_deref(), Some("- starts like a list: 'quoted' & more"));
+        assert_eq!(field("canonical").as_deref(), Some("https://example.com/canonical"));
+        assert_eq!(field("lang").as_deref(), Some("th"));
+        assert_eq!(field("lastmod").as_deref(), Some("2024-01-02"));
+        assert_eq!(field("fetch_mode").as_deref(), Some("HttpRequest"));
+        assert_eq!(field("content_hash"), Some(content_hash("# Body\n")));
+    }
+
+    #[test]
+    fn missing_fields_are_omitted() {
+        let meta = FrontMatter::from_page(&page("<html lang='en'><body><p>x</p></body></html>"), Some("  "));
+        let parsed = yaml(split_front_matter(&meta.prepend("x").unwrap()).0.unwrap());
+        for key in ["title", "description", "canonical", "lastmod"] {
+            assert!(!parsed.contains_key(key), "{}", key);
+        }
+        assert_eq!(parsed.get("lang").and_then(|v| v.as_str()), Some("en"));
+    }
+
+    #[test]
+    fn split_front_matter_requires_opening_and_closing_markers() {
+        assert_eq!(split_front_matter("# No front matter\n"), (None, "# No front matter\n"));
+        assert_eq!(split_front_matter("---\ntitle: x\nunclosed\n"), (None, "---\ntitle: x\nunclosed\n"));
+        assert_eq!(split_front_matter("---\na: 1\n---\n\nbody\n---\n"), (Some("---\na: 1\n---\n\n"), "body\n---\n"));
+    }
+
+    #[test]
+    fn update_content_hash_matches_hash_of_rewritten_body() {
+        let meta = FrontMatter::from_page(&page("<title>T</title>"), None);
+        let before = "See [Learn](https://example.com/learn).\n";
+        let after = "See [Learn](learn.md).\n";
+        let file = meta.prepend(before).unwrap();
+        let (front, body) = split_front_matter(&file);
+        assert_eq!(body, before);
+
+        let updated = update_content_hash(front.unwrap(), after) + after;
+        assert_eq!(updated, meta.prepend(after).unwrap());
+        assert_ne!(updated, file);
+        // hash ขึ้นกับเนื้อหาเท่านั้น ค่าคงที่ข้ามการรัน
+        assert_eq!(content_hash(""), "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(update_content_hash(front.unwrap(), before) + before, file);
+    }
+}
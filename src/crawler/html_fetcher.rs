@@ -1,7 +1,20 @@
+use crate::crawler::charset::{decode, detect_encoding, CharsetSource};
+use crate::config::config::AppConfig;
 use crate::crawler::chrome_fetcher;
+use crate::crawler::html_to_markdown::{page_to_markdown, ConvertOptions};
+use crate::crawler::rate_limiter::RateLimiter;
+use crate::crawler::redirect::{redirect_target, RedirectRules};
+use crate::crawler::robots::RobotsCache;
+use crate::crawler::retry::{is_retryable_status, scrape_with_retry, status_reason, RetryPolicy};
+use chrono::{DateTime, Utc};
+use encoding_rs::{Encoding, UTF_8};
+use spider::reqwest::header::HeaderMap;
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
 
 /// โหมดการโหลด HTML
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum FetchMode {
     HttpRequest,
     Chrome,
@@ -16,17 +29,321 @@ impl FetchMode {
     }
 }
 
+/// URL ที่โหลดไม่สำเร็จหลัง retry ครบแล้ว พร้อมเหตุผลสุดท้าย
+#[derive(Debug, Clone)]
+pub struct FetchFailure {
+    pub url: String,
+    pub reason: String,
+}
+
+impl fmt::Display for FetchFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.url, self.reason)
+    }
+}
+
+/// หน้าเว็บที่โหลดได้ (ได้ response แล้ว ไม่ว่า status จะเป็นอะไร)
+/// ส่งต่อทั้ง struct ให้ขั้นตอนถัดไป (filter, แปลง markdown, เขียนไฟล์) ใช้ตัดสินใจ
+/// - url: URL ที่ขอ (จาก sitemap)
+/// - final_url: URL สุดท้ายหลัง redirect
+/// - content_type: mime type จาก Content-Type (ตัวพิมพ์เล็ก ไม่มี parameter)
+///   ถ้า server ไม่ส่งมา เดาจาก magic bytes และนามสกุลใน URL
+/// - charset: charset ที่ตรวจพบ (ชื่อตาม WHATWG เช่น "utf-8", "windows-874") และที่มา
+/// - bytes: body ดิบตามที่ได้รับ
+/// - duration: เวลาที่ใช้โหลด (รวม retry และการรอ rate limit)
+/// - mode: FetchMode ที่ใช้โหลดจริง
+/// - fetched_at: เวลาที่ได้ response
+/// - redirect_chain: hop ก่อนถึง final_url ตามลำดับ (status 3xx, URL ที่ตอบ redirect)
+///   Chrome ไม่คืน hop ระหว่างทาง จึงมีแค่ (0, url) เมื่อ final_url ต่างจาก url (0 = ไม่ทราบ status)
+///   ว่างถ้าไม่มี redirect หรือโหลดด้วย Chrome (browser follow redirect เองโดยไม่บอก hop)
+#[derive(Debug, Clone)]
+pub struct FetchedPage {
+    pub url: String,
+    pub final_url: String,
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub content_type: String,
+    pub charset: String,
+    pub charset_source: CharsetSource,
+    pub bytes: Vec<u8>,
+    pub duration: Duration,
+    pub mode: FetchMode,
+    pub fetched_at: DateTime<Utc>,
+    pub redirect_chain: Vec<(u16, String)>,
+}
+
+impl FetchedPage {
+    /// สร้างจาก Page ของ spider
+    pub fn from_spider_page(url: &str, page: &spider::page::Page, mode: FetchMode, duration: Duration) -> Self {
+        let headers = page.headers.clone().unwrap_or_default();
+        let (content_type, header_charset) = headers
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .map(parse_content_type)
+            .unwrap_or_default();
+        let bytes = page.get_html_bytes_u8().to_vec();
+        let content_type = content_type.unwrap_or_else(|| sniff_content_type(&bytes, page.get_url_final()));
+        let (encoding, charset_source) = match mode {
+            FetchMode::Chrome => (UTF_8, CharsetSource::Browser),
+            FetchMode::HttpRequest => detect_encoding(&bytes, header_charset.as_deref(), page.get_url_final()),
+        };
+        Self {
+            url: url.to_string(),
+            final_url: page.get_url_final().to_string(),
+            status: page.status_code.as_u16(),
+            headers,
+            content_type,
+            charset: encoding.name().to_ascii_lowercase(),
+            charset_source,
+            bytes,
+            duration,
+            mode,
+            fetched_at: Utc::now(),
+            redirect_chain: Vec::new(),
+        }
+    }
+
+    /// body ที่ decode เป็น UTF-8 ตาม charset ที่ตรวจพบแล้ว
+    pub fn html(&self) -> String {
+        let encoding = Encoding::for_label(self.charset.as_bytes()).unwrap_or(UTF_8);
+        decode(&self.bytes, encoding)
+    }
+
+    /// content type เป็น HTML (หน้าเว็บปกติ)
+    pub fn is_html(&self) -> bool {
+        matches!(self.content_type.as_str(), "text/html" | "application/xhtml+xml")
+    }
+
+    /// status 2xx
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// redirect chain สำหรับ log เช่น "301 http://a.com/ -> 302 https://a.com/ -> https://a.com/th/"
+    pub fn describe_redirects(&self) -> String {
+        let mut out = String::new();
+        for (status, url) in &self.redirect_chain {
+            match status {
+                0 => out.push_str(&format!("{} -> ", url)),
+                _ => out.push_str(&format!("{} {} -> ", status, url)),
+            }
+        }
+        out.push_str(&self.final_url);
+        out
+    }
+}
+
+/// ผลการโหลดของแต่ละ URL: FetchedPage หรือเหตุผลที่ล้มเหลว
+pub type FetchResult = Result<FetchedPage, FetchFailure>;
+
+/// แปลง FetchedPage เป็น markdown สำหรับ content type หนึ่ง ๆ
+/// ใช้เพิ่มการรองรับไฟล์ชนิดอื่นนอกจาก HTML ผ่าน ContentDispatcher::register
+pub trait ContentHandler {
+    /// ชื่อสำหรับ log
+    fn name(&self) -> &str;
+    /// รองรับ content type นี้หรือไม่ (mime type ตัวพิมพ์เล็ก ไม่มี parameter)
+    fn handles(&self, content_type: &str) -> bool;
+    /// แปลงเป็น markdown
+    fn to_markdown(&self, page: &FetchedPage) -> Result<String, Box<dyn Error>>;
+}
+
+/// handler มาตรฐานสำหรับหน้า HTML
+pub struct HtmlHandler {
+    pub options: ConvertOptions,
+}
+
+impl ContentHandler for HtmlHandler {
+    fn name(&self) -> &str {
+        "html"
+    }
+
+    fn handles(&self, content_type: &str) -> bool {
+        matches!(content_type, "text/html" | "application/xhtml+xml")
+    }
+
+    fn to_markdown(&self, page: &FetchedPage) -> Result<String, Box<dyn Error>> {
+        Ok(page_to_markdown(page, &self.options))
+    }
+}
+
+/// content type ที่ข้ามโดย default ("*" ท้าย = ทุก subtype)
+const DEFAULT_SKIP_CONTENT_TYPES: &[&str] = &[
+    "image/*",
+    "video/*",
+    "audio/*",
+    "font/*",
+    "application/zip",
+    "application/gzip",
+    "application/octet-stream",
+];
+
+/// เลือก handler ตาม content type ของแต่ละหน้า
+/// - skip_content_types: content type ที่ข้ามเสมอ (ตั้งค่าได้ใน app.yaml)
+/// - handlers: ลองตามลำดับที่ register ตัวแรกที่รองรับได้ใช้
+pub struct ContentDispatcher {
+    skip_content_types: Vec<String>,
+    handlers: Vec<Box<dyn ContentHandler>>,
+}
+
+impl ContentDispatcher {
+    /// dispatcher ที่มี HtmlHandler (และ PdfHandler เมื่อเปิด feature "pdf") ไว้แล้ว
+    /// html_options: ตัวเลือกการแปลง HTML ของโดเมนที่กำลัง crawl
+    pub fn from_config(cfg: &AppConfig, html_options: ConvertOptions) -> Self {
+        let skip = cfg
+            .skip_content_types
+            .clone()
+            .unwrap_or_else(|| DEFAULT_SKIP_CONTENT_TYPES.iter().map(|t| t.to_string()).collect());
+        let mut dispatcher = Self {
+            skip_content_types: skip.into_iter().map(|t| t.trim().to_ascii_lowercase()).collect(),
+            handlers: Vec::new(),
+        };
+        dispatcher.register(Box::new(HtmlHandler { options: html_options }));
+        #[cfg(feature = "pdf")]
+        dispatcher.register(Box::new(crate::crawler::pdf_handler::PdfHandler));
+        dispatcher
+    }
+
+    /// เพิ่ม handler สำหรับ content type อื่น
+    pub fn register(&mut self, handler: Box<dyn ContentHandler>) {
+        println!("[html_fetcher] register content handler: {}", handler.name());
+        self.handlers.push(handler);
+    }
+
+    /// content type อยู่ใน skip_content_types หรือไม่
+    pub fn is_skipped(&self, content_type: &str) -> bool {
+        self.skip_content_types.iter().any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => content_type.starts_with(prefix),
+            None => content_type == pattern,
+        })
+    }
+
+    /// handler ของ content type นี้ (None = ข้าม หรือไม่มี handler รองรับ)
+    pub fn handler_for(&self, content_type: &str) -> Option<&dyn ContentHandler> {
+        if self.is_skipped(content_type) {
+            return None;
+        }
+        self.handlers
+            .iter()
+            .find(|h| h.handles(content_type))
+            .map(|h| h.as_ref())
+    }
+}
+
+/// เดา content type จากนามสกุลไฟล์ใน URL (ใช้ข้ามไฟล์ก่อนโหลด และตอน server ไม่ส่ง Content-Type)
+pub fn guess_content_type_from_url(url: &str) -> Option<&'static str> {
+    let path = spider::url::Url::parse(url).ok()?.path().to_ascii_lowercase();
+    let ext = path.rsplit_once('.')?.1;
+    let guess = match ext {
+        "html" | "htm" | "php" | "asp" | "aspx" | "jsp" => "text/html",
+        "xhtml" => "application/xhtml+xml",
+        "pdf" => "application/pdf",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mp3" => "audio/mpeg",
+        "zip" => "application/zip",
+        "gz" | "tgz" => "application/gzip",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "txt" => "text/plain",
+        "xml" => "application/xml",
+        "json" => "application/json",
+        "doc" => "application/msword",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "xls" => "application/vnd.ms-excel",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        _ => return None,
+    };
+    Some(guess)
+}
+
+/// เดา content type เมื่อไม่มี Content-Type header: magic bytes -> นามสกุลใน URL -> text/html
+fn sniff_content_type(bytes: &[u8], url: &str) -> String {
+    let sniffed = if bytes.starts_with(b"%PDF-") {
+        Some("application/pdf")
+    } else if bytes.starts_with(b"\x89PNG") {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF8") {
+        Some("image/gif")
+    } else if bytes.starts_with(b"PK\x03\x04") {
+        Some("application/zip")
+    } else {
+        None
+    };
+    sniffed
+        .or_else(|| guess_content_type_from_url(url))
+        .unwrap_or("text/html")
+        .to_string()
+}
+
+/// แยก Content-Type เป็น (mime type, charset)
+/// เช่น "text/html; charset=TIS-620" -> ("text/html", "tis-620")
+pub fn parse_content_type(value: &str) -> (Option<String>, Option<String>) {
+    let mut parts = value.split(';');
+    let mime = parts
+        .next()
+        .map(|m| m.trim().to_ascii_lowercase())
+        .filter(|m| !m.is_empty());
+    let charset = parts.find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case("charset") {
+            return None;
+        }
+        let value = value.trim().trim_matches(|c| c == '"' || c == '\'').to_ascii_lowercase();
+        (!value.is_empty()).then_some(value)
+    });
+    (mime, charset)
+}
+
+/// แปลง page ที่ได้จาก scrape_with_retry เป็น FetchResult
+/// status ที่ยัง retry ได้หลังลองครบแล้ว (เช่น 503 ตลอด) ถือว่าล้มเหลว
+pub(crate) fn page_result(
+    url: &str,
+    page: Result<spider::page::Page, String>,
+    retry: &RetryPolicy,
+    mode: FetchMode,
+    duration: Duration,
+) -> FetchResult {
+    let page = page.map_err(|reason| FetchFailure {
+        url: url.to_string(),
+        reason,
+    })?;
+    let status = page.status_code.as_u16();
+    if is_retryable_status(status) {
+        return Err(FetchFailure {
+            url: url.to_string(),
+            reason: format!("{} หลังลอง {} ครั้ง", status_reason(status), retry.max_attempts),
+        });
+    }
+    Ok(FetchedPage::from_spider_page(url, &page, mode, duration))
+}
+
 /// โหลด HTML จาก URLs โดยเลือกระหว่าง HttpRequest หรือ Chrome (spider / spider_chrome)
 /// - urls: รายการ URL ที่จะโหลด
 /// - mode: FetchMode::HttpRequest หรือ FetchMode::Chrome
 /// - user_agent: user agent string
-/// - delay_ms: delay ระหว่างการโหลดแต่ละ URL
+/// - limiter: rate limiter ต่อ host ที่ใช้ร่วมกันทุก fetcher
+/// - retry: นโยบาย retry (backoff + Retry-After)
+/// - redirects: จำนวน hop สูงสุดและ host ที่ redirect ไปได้
+/// - robots: robots.txt ของแต่ละ host (HttpRequest ตรวจปลายทางของทุก hop ก่อน follow)
+///
+/// คืนผลหนึ่งรายการต่อ URL ตามลำดับเดิม
 pub async fn fetch_html_from_urls(
     urls: Vec<String>,
     mode: FetchMode,
     user_agent: &str,
-    delay_ms: u64,
-) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
+    limiter: &RateLimiter,
+    retry: &RetryPolicy,
+    redirects: &RedirectRules,
+    robots: &RobotsCache,
+) -> Result<Vec<FetchResult>, Box<dyn std::error::Error>> {
     let mode_label = match mode {
         FetchMode::Chrome => "SPA (Chrome/JavaScript)",
         FetchMode::HttpRequest => "SSR (HttpRequest)",
@@ -37,81 +354,116 @@ pub async fn fetch_html_from_urls(
         FetchMode::Chrome => {
             // ใช้ chrome_fetcher สำหรับโหมด Chrome
             println!("[html_fetcher] ⚡ SPA Mode - using fetch_with_chrome function");
-            chrome_fetcher::fetch_with_chrome(urls, user_agent, delay_ms).await
+            chrome_fetcher::fetch_with_chrome(urls, user_agent, limiter, retry, redirects).await
         }
         FetchMode::HttpRequest => {
             // ใช้ HttpRequest แบบเดิมสำหรับโหมด SSR
             println!("[html_fetcher] 📄 SSR Mode - using basic HTTP fetch (no JavaScript)");
-            fetch_with_http_request(urls, user_agent, delay_ms).await
+            fetch_with_http_request(urls, user_agent, limiter, retry, redirects, robots).await
         }
     }
 }
 
 /// โหลด HTML โดยใช้ HttpRequest (สำหรับ SSR)
+/// follow redirect เองทีละ hop (แต่ละ hop ผ่าน rate limiter/retry) เพื่อเก็บ redirect chain
+/// ปลายทางของแต่ละ hop ต้องผ่าน cross_host policy และ robots.txt ก่อนโหลด ไม่ผ่าน = ล้มเหลวที่ hop นั้น
 async fn fetch_with_http_request(
     urls: Vec<String>,
     user_agent: &str,
-    delay_ms: u64,
-) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
-    use std::time::Duration;
+    limiter: &RateLimiter,
+    retry: &RetryPolicy,
+    redirects: &RedirectRules,
+    robots: &RobotsCache,
+) -> Result<Vec<FetchResult>, Box<dyn std::error::Error>> {
     use spider::website::Website;
     use spider::compact_str::CompactString;
-    use tokio::time::sleep;
 
     let mut results = Vec::new();
 
     for url in urls {
         println!("[html_fetcher] start -> {}", url);
 
-        let mut website = Website::new(&url);
+        let t0 = std::time::Instant::now();
+        let mut current = url.clone();
+        let mut chain: Vec<(u16, String)> = Vec::new();
+        let result = loop {
+            // spider เก็บผลไว้ใน Website จึงสร้างใหม่ทุกครั้งที่ retry
+            let make_website = || {
+                let mut website = Website::new(&current);
+
+                // ตั้ง user-agent (Box<CompactString> ตามที่ spider ต้องการ)
+                website.configuration.user_agent = Some(Box::new(CompactString::new(user_agent)));
 
-        // ตั้ง user-agent (Box<CompactString> ตามที่ spider ต้องการ)
-        website.configuration.user_agent = Some(Box::new(CompactString::new(user_agent)));
+                // โหลดแค่หน้านั้น ๆ
+                website.with_depth(0);
 
-        // โหลดแค่หน้านั้น ๆ
-        website.with_depth(0);
+                // ไม่ให้ spider follow redirect เอง (follow ทีละ hop ด้านล่าง)
+                redirects.apply_manual(&mut website);
 
-        // ตั้ง delay ถ้ามี (spider configuration)
-        website.configuration.delay = delay_ms;
+                // Log internal configuration for visibility
+                // (ใส่ URL ไว้ด้วย เพราะหลาย worker พิมพ์ log สลับกัน)
+                println!(
+                    "[html_fetcher] config -> url={}, user_agent={:?}, depth={}",
+                    current,
+                    website.configuration.user_agent.as_ref().map(|b| b.as_ref()),
+                    website.configuration.depth
+                );
+                website
+            };
 
-        // Log internal configuration for visibility
-        println!(
-            "[html_fetcher] config -> user_agent={:?}, delay_ms={}, depth={}",
-            website.configuration.user_agent.as_ref().map(|b| b.as_ref()),
-            website.configuration.delay,
-            website.configuration.depth
-        );
+            // รอคิวของ host ตาม rate limit และ retry เมื่อได้ 429/5xx หรือ timeout
+            println!("[html_fetcher] scrape start: {}", current);
+            let page = scrape_with_retry(&current, limiter, retry, make_website).await;
+            println!("[html_fetcher] scrape done: {} (took {:?})", current, t0.elapsed());
 
-        // เรียก scrape / crawl (spider API) — ใช้ await
-        let t0 = std::time::Instant::now();
-        println!("[html_fetcher] scrape start: {}", url);
-        website.scrape().await;
-        let took = t0.elapsed();
-        println!("[html_fetcher] scrape done: {} (took {:?})", url, took);
-
-        // พิมพ์ข้อมูล pages ที่ได้ (debug)
-        if let Some(pages) = website.get_pages() {
-            println!("[html_fetcher] pages returned: {}", pages.len());
-            for (i, page) in pages.iter().enumerate() {
-                println!("  [page {}] url={} (html_len={})", i + 1, page.get_url(), page.get_html().len());
+            // 3xx ที่มี Location -> จด hop แล้วโหลดปลายทางต่อ
+            let next = page.as_ref().ok().and_then(|p| Some((p.status_code.as_u16(), redirect_target(&current, p)?)));
+            let Some((status, next)) = next else {
+                break page_result(&url, page, retry, FetchMode::HttpRequest, t0.elapsed()).map(|mut fetched| {
+                    fetched.redirect_chain = std::mem::take(&mut chain);
+                    fetched
+                });
+            };
+            println!("[html_fetcher] redirect {} {} -> {}", status, current, next);
+            if !redirects.allows(&url, &next) {
+                break Err(FetchFailure {
+                    url: url.clone(),
+                    reason: format!("redirect ไป host ที่ cross_host_redirects ไม่อนุญาต: {}", next),
+                });
             }
-            if let Some(page) = pages.first() {
-                let html = page.get_html().to_string();
-                println!("[html_fetcher] fetched {} bytes from {}", html.len(), url);
-                results.push((url.clone(), html));
-            } else {
-                eprintln!("[html_fetcher] no page for url: {}", url);
+            if !robots.is_allowed(&next).await {
+                break Err(FetchFailure {
+                    url: url.clone(),
+                    reason: format!("redirect ไป URL ที่ robots.txt ไม่อนุญาต: {}", next),
+                });
             }
-        } else {
-            eprintln!("[html_fetcher] get_pages returned None for url: {}", url);
-        }
-
-        // delay ระหว่าง requests
-        if delay_ms > 0 {
-            sleep(Duration::from_millis(delay_ms)).await;
+            chain.push((status, std::mem::replace(&mut current, next)));
+            if chain.len() > redirects.max_redirects {
+                break Err(FetchFailure {
+                    url: url.clone(),
+                    reason: format!("redirect เกิน max_redirects ({} hop)", redirects.max_redirects),
+                });
+            }
+        };
+        match &result {
+            Ok(page) => println!(
+                "[html_fetcher] fetched {} bytes from {} (HTTP {}, content_type={}, charset={} [{:?}], final_url={})",
+                page.bytes.len(),
+                url,
+                page.status,
+                page.content_type,
+                page.charset,
+                page.charset_source,
+                page.final_url
+            ),
+            Err(failure) => eprintln!("[html_fetcher] failed {}", failure),
         }
+        results.push(result);
     }
 
-    println!("[html_fetcher] finished, got {} pages", results.len());
+    println!(
+        "[html_fetcher] finished, got {} pages",
+        results.iter().filter(|r| r.is_ok()).count()
+    );
     Ok(results)
-}
\ No newline at end of file
+}
@@ -1,270 +1,935 @@
+use ego_tree::{NodeId, NodeRef};
+use scraper::{ElementRef, Html, Node, Selector};
+use spider::url::Url;
 
+use super::domain_detector::DomainDetector;
+use super::html_fetcher::FetchedPage;
+use super::main_content::extract_main_content;
+use crate::config::config::AppConfig;
+
+/// วิธีแปลงตารางที่เป็น pipe table ไม่ได้ (มี colspan/rowspan หรือมี block ซ้อนในช่อง)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TableFallback {
+    /// เก็บเป็น HTML ดิบ (GitHub และ static site generator ส่วนใหญ่แสดงได้)
+    #[default]
+    Html,
+    /// แปลงเป็นรายการ แถวละหนึ่งข้อ ช่องเป็นข้อย่อย "หัวคอลัมน์: ค่า"
+    List,
+}
+
+impl TableFallback {
+    pub fn from_name(s: &str) -> Self {
+        match s {
+            "list" => TableFallback::List,
+            _ => TableFallback::Html,
+        }
+    }
+}
+
+/// ตัวเลือกการแปลง HTML -> markdown
+/// - extract_main_content: ตัดเฉพาะเนื้อหาหลัก (ไม่เอาเมนู, header, footer, แถบข้าง)
+/// - content_selector: CSS selector ของกล่องเนื้อหา ถ้าระบุจะตัดเนื้อหาหลักด้วย selector นี้ก่อน
+/// - table_fallback: วิธีแปลงตารางที่ซับซ้อนเกินกว่า pipe table
+#[derive(Debug, Clone, Default)]
+pub struct ConvertOptions {
+    pub extract_main_content: bool,
+    pub content_selector: Option<String>,
+    pub table_fallback: TableFallback,
+}
+
+impl ConvertOptions {
+    /// ค่า default จาก app.yaml, ค่าเฉพาะโดเมนจาก whitelist.yaml
+    pub fn from_config(cfg: &AppConfig, detector: &DomainDetector, domain: &str) -> Self {
+        let content_selector = detector.content_selector(domain);
+        Self {
+            extract_main_content: detector
+                .extract_main_content(domain)
+                .unwrap_or(cfg.extract_main_content.unwrap_or(false))
+                || content_selector.is_some(),
+            content_selector,
+            table_fallback: TableFallback::from_name(cfg.table_fallback.as_deref().unwrap_or("html")),
+        }
+    }
+}
+
+/// แปลงหน้าเว็บที่โหลดได้เป็น markdown (อ้างอิง URL สุดท้ายหลัง redirect)
+pub fn page_to_markdown(page: &FetchedPage, options: &ConvertOptions) -> String {
+    convert_html(&page.final_url, &page.html(), options)
+}
+
+/// tag ที่ไม่มีเนื้อหาที่อ่านได้ ข้ามทั้ง subtree
+const SKIPPED_TAGS: &[&str] = &[
+    "head", "script", "style", "noscript", "template", "svg", "iframe", "canvas", "object",
+];
+
+/// tag ระดับ block (ขึ้นย่อหน้าใหม่ก่อนและหลัง) นอกนั้นถือเป็น inline
+const BLOCK_TAGS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "body", "caption", "center", "dd", "details", "dialog",
+    "div", "dl", "dt", "fieldset", "figcaption", "figure", "footer", "form", "h1", "h2", "h3", "h4", "h5",
+    "h6", "header", "hgroup", "hr", "html", "li", "main", "menu", "nav", "ol", "p", "pre", "section",
+    "summary", "table", "tbody", "td", "tfoot", "th", "thead", "tr", "ul",
+];
+
+/// แปลง HTML เป็น markdown
+/// parse ด้วย html5ever (แบบเดียวกับ browser จึงรับ markup ที่ปิด tag ไม่ครบได้) แล้วเดิน DOM tree
+/// - block element -> ย่อหน้าคั่นด้วยบรรทัดว่าง
+/// - inline element -> ข้อความในย่อหน้าเดียวกัน (whitespace ยุบเหลือช่องเดียวแบบ browser)
+/// - extract_main_content: แปลงเฉพาะเนื้อหาหลัก ถ้าตัดแล้วไม่เหลือข้อความจะแปลงทั้งหน้าแทน
+/// - href/src แบบ relative resolve กับ url (หรือ `<base href>` ของหน้า) เป็น URL เต็ม
+pub fn convert_html(url: &str, html: &str, options: &ConvertOptions) -> String {
+    let mut text = String::new();
+
+    if options.extract_main_content {
+        let mut document = Html::parse_document(html);
+        let converter = Converter { options, base: document_base(&document, url) };
+        let roots = extract_main_content(&mut document, options.content_selector.as_deref());
+        text = converter
+            .blocks(roots.into_iter().filter_map(|id| document.tree.get(id)))
+            .join("\n\n");
+        if text.trim().is_empty() {
+            println!("[main_content] ไม่พบเนื้อหาหลักใน {} แปลงทั้งหน้าแทน", url);
+        }
+    }
+    if text.trim().is_empty() {
+        let document = Html::parse_document(html);
+        let converter = Converter { options, base: document_base(&document, url) };
+        text = converter.blocks(document.tree.root().children()).join("\n\n");
+    }
 
-pub fn html_to_markdown(url: &str, html: &str) -> String {
-    let mut output = String::new();
-    
-    // Remove script and style tags completely
-    let html = remove_tags(html, &["script", "style", "noscript"]);
-    
-    // Convert common tags to markdown
-    let html = convert_headings(&html);
-    let html = convert_links(&html);
-    let html = convert_images(&html);
-    let html = convert_strong(&html);
-    let html = convert_em(&html);
-    let html = convert_lists(&html);
-    let html = convert_blockquotes(&html);
-    let html = convert_code(&html);
-    
-    // Remove remaining HTML tags
-    let text = strip_html_tags(&html);
-    
-    // Clean up whitespace
-    let text = clean_whitespace(&text);
-    
     if text.trim().is_empty() {
         format!("# {}\n\nNo content extracted.\n", url)
     } else {
-        text
+        text.trim().to_string()
     }
 }
 
-fn remove_tags(html: &str, tags: &[&str]) -> String {
-    let mut result = html.to_string();
-    for tag in tags {
-        let open = format!("<{}", tag);
-        let close = format!("</{}>", tag);
-        
-        while let Some(start) = result.find(&open) {
-            if let Some(end_pos) = result[start..].find(&close) {
-                result.replace_range(start..start + end_pos + close.len(), "");
-            } else {
-                break;
+/// URL ที่ใช้ resolve link ของหน้า: `<base href>` แรก (resolve กับ url ของหน้าอีกที) หรือ url ของหน้า
+fn document_base(document: &Html, url: &str) -> Option<Url> {
+    let page = Url::parse(url).ok()?;
+    let selector = Selector::parse("base[href]").ok()?;
+    let base = document
+        .select(&selector)
+        .next()
+        .and_then(|el| el.value().attr("href"))
+        .and_then(|href| page.join(href.trim()).ok());
+    Some(base.unwrap_or(page))
+}
+
+/// ตัวแปลง DOM -> markdown
+struct Converter<'o> {
+    options: &'o ConvertOptions,
+    /// URL สำหรับ resolve link (None = url ของหน้า parse ไม่ได้ ใช้ค่าเดิม)
+    base: Option<Url>,
+}
+
+impl Converter<'_> {
+    /// resolve URL ของรูป/ไฟล์เป็น URL เต็ม
+    fn resolve(&self, reference: &str) -> String {
+        match self.base.as_ref().and_then(|base| base.join(reference).ok()) {
+            Some(url) => url.to_string(),
+            None => reference.to_string(),
+        }
+    }
+
+    /// ปลายทางของ link เป็น URL เต็ม (link ไปตำแหน่งในหน้าเดียวกัน #id คงไว้ตามเดิม)
+    /// internal_links = local จะเปลี่ยน link ไปหน้าที่บันทึกแล้วเป็นไฟล์ .md ทีหลังใน link_rewriter
+    fn link_target(&self, href: &str) -> String {
+        if href.starts_with('#') {
+            return href.to_string();
+        }
+        self.resolve(href)
+    }
+
+    /// แปลง node ต่อกันเป็นรายการ block
+    /// ข้อความ inline ที่อยู่ติดกันรวมเป็นย่อหน้าเดียว จนกว่าจะเจอ block element
+    fn blocks<'a>(&self, nodes: impl Iterator<Item = NodeRef<'a, Node>>) -> Vec<String> {
+        let mut blocks: Vec<String> = Vec::new();
+        let mut inline = String::new();
+        // ชนิดของรายการที่เป็น block ล่าสุด (ul/ol)
+        let mut last_list: Option<&str> = None;
+        for node in nodes {
+            match ElementRef::wrap(node) {
+                Some(el) if SKIPPED_TAGS.contains(&el.value().name()) => {}
+                Some(el) if BLOCK_TAGS.contains(&el.value().name()) => {
+                    if push_paragraph(&mut blocks, &inline) {
+                        last_list = None;
+                    }
+                    inline.clear();
+                    let before = blocks.len();
+                    let converted: Vec<String> = self.block(el).into_iter().filter(|b| !b.trim().is_empty()).collect();
+                    let name = el.value().name();
+                    let is_list = matches!(name, "ul" | "ol");
+                    // รายการชนิดเดียวกันที่อยู่ติดกันจะถูกรวมเป็นรายการเดียว (เลขต่อกัน) จึงคั่นด้วย comment
+                    if is_list && !converted.is_empty() && last_list == Some(name) {
+                        blocks.push("<!-- -->".to_string());
+                    }
+                    blocks.extend(converted);
+                    if blocks.len() > before {
+                        last_list = is_list.then_some(name);
+                    }
+                }
+                _ => inline.push_str(&self.inline(node)),
             }
         }
+        push_paragraph(&mut blocks, &inline);
+        blocks
     }
-    result
-}
 
-fn convert_headings(html: &str) -> String {
-    let mut result = html.to_string();
-    for level in 1..=6 {
-        let open = format!("<h{}", level);
-        let close = format!("</h{}>", level);
-        let marker = "#".repeat(level);
-        
-        while let Some(start) = result.find(&open) {
-            if let Some(tag_end) = result[start..].find('>') {
-                let content_start = start + tag_end + 1;
-                if let Some(close_start) = result[content_start..].find(&close) {
-                    let content = &result[content_start..content_start + close_start];
-                    let markdown = format!("\n\n{} {}\n\n", marker, strip_html_tags(content).trim());
-                    result.replace_range(start..content_start + close_start + close.len(), &markdown);
+    /// แปลง block element หนึ่งตัว
+    fn block(&self, el: ElementRef) -> Vec<String> {
+        let name = el.value().name();
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = usize::from(name.as_bytes()[1] - b'0');
+                let text = collapse_lines(&self.inline_children(el)).replace('\n', " ");
+                if text.is_empty() {
+                    Vec::new()
                 } else {
-                    break;
+                    vec![format!("{} {}", "#".repeat(level), text)]
                 }
-            } else {
-                break;
             }
+            "hr" => vec!["---".to_string()],
+            "pre" => code_block(el).into_iter().collect(),
+            "blockquote" => {
+                let inner = self.blocks(el.children()).join("\n\n");
+                let quoted: Vec<String> = inner
+                    .lines()
+                    .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {}", line) })
+                    .collect();
+                vec![quoted.join("\n")]
+            }
+            "ul" | "ol" => vec![self.list(el)],
+            "table" => self.table(el),
+            _ => self.blocks(el.children()),
         }
     }
-    result
-}
 
-fn convert_links(html: &str) -> String {
-    let mut result = html.to_string();
-    while let Some(start) = result.find("<a ") {
-        if let Some(href_start) = result[start..].find("href=\"") {
-            let href_pos = start + href_start + 6;
-            if let Some(href_end) = result[href_pos..].find('"') {
-                let href = &result[href_pos..href_pos + href_end];
-                if let Some(tag_end) = result[start..].find('>') {
-                    let content_start = start + tag_end + 1;
-                    if let Some(close) = result[content_start..].find("</a>") {
-                        let text = &result[content_start..content_start + close];
-                        let markdown = format!("[{}]({})", strip_html_tags(text).trim(), href);
-                        result.replace_range(start..content_start + close + 4, &markdown);
-                        continue;
+    /// แปลง <table> เป็น GFM pipe table
+    /// - แถวหัวตาราง: แถวแรกใน <thead> หรือแถวแรกที่เป็น <th> ทั้งแถว (ไม่มี = หัวตารางว่าง)
+    /// - ตารางที่มีช่องเดียว (ใช้จัด layout) แปลงเป็นเนื้อหาของช่องนั้น
+    /// - มี colspan/rowspan หรือ block ซ้อนในช่อง -> ใช้ table_fallback
+    fn table(&self, el: ElementRef) -> Vec<String> {
+        let rows = table_rows(el);
+        let cells: Vec<Vec<ElementRef>> = rows
+            .iter()
+            .map(|row| {
+                row.child_elements()
+                    .filter(|c| matches!(c.value().name(), "td" | "th"))
+                    .collect()
+            })
+            .collect();
+        let cell_count: usize = cells.iter().map(Vec::len).sum();
+        if cell_count == 0 {
+            return self.blocks(el.children());
+        }
+        if cell_count == 1 {
+            return self.blocks(cells.concat()[0].children());
+        }
+
+        let mut blocks = Vec::new();
+        if let Some(caption) = el.child_elements().find(|c| c.value().name() == "caption") {
+            push_paragraph(&mut blocks, &self.inline_children(caption));
+        }
+
+        let header = rows.first().is_some_and(|row| {
+            row.parent().and_then(ElementRef::wrap).is_some_and(|p| p.value().name() == "thead")
+                || cells[0].iter().all(|c| c.value().name() == "th")
+        });
+        let complex = cells.iter().flatten().any(|cell| {
+            span(*cell, "colspan") > 1 || span(*cell, "rowspan") > 1 || has_block_content(*cell)
+        });
+
+        if !complex {
+            blocks.push(self.pipe_table(&cells, header));
+        } else {
+            match self.options.table_fallback {
+                TableFallback::Html => blocks.push(raw_html_block(&self.resolved_html(el))),
+                TableFallback::List => blocks.push(self.table_as_list(&cells, header)),
+            }
+        }
+        blocks
+    }
+
+    /// HTML ของ element ที่ href/src ทุกตัว resolve เป็น URL เต็มแล้ว (แบบเดียวกับ link ใน markdown)
+    fn resolved_html(&self, el: ElementRef) -> String {
+        let mut fragment = Html::parse_fragment(&el.html());
+        let ids: Vec<NodeId> = fragment.root_element().descendent_elements().map(|e| e.id()).collect();
+        for id in ids {
+            let Some(mut node) = fragment.tree.get_mut(id) else {
+                continue;
+            };
+            if let Node::Element(element) = node.value() {
+                for (name, value) in element.attrs.iter_mut() {
+                    if matches!(&*name.local, "href" | "src") {
+                        *value = self.link_target(value.trim()).into();
                     }
                 }
             }
         }
-        break;
+        fragment.root_element().inner_html()
     }
-    result
-}
 
-fn convert_images(html: &str) -> String {
-    let mut result = html.to_string();
-    while let Some(start) = result.find("<img ") {
-        if let Some(end) = result[start..].find('>') {
-            let tag = &result[start..start + end + 1];
-            let src = extract_attr(tag, "src");
-            let alt = extract_attr(tag, "alt");
-            let markdown = format!("\n\n![{}]({})\n\n", alt, src);
-            result.replace_range(start..start + end + 1, &markdown);
-        } else {
-            break;
+    fn pipe_table(&self, cells: &[Vec<ElementRef>], header: bool) -> String {
+        let columns = cells.iter().map(Vec::len).max().unwrap_or(0);
+        let row_text = |row: &[String]| {
+            let mut padded = row.to_vec();
+            padded.resize(columns, String::new());
+            format!("| {} |", padded.join(" | "))
+        };
+        let mut rows: Vec<Vec<String>> = cells
+            .iter()
+            .map(|row| row.iter().map(|cell| self.table_cell(*cell)).collect())
+            .collect();
+
+        let head = if header { rows.remove(0) } else { Vec::new() };
+        let mut lines = vec![row_text(&head), format!("|{}", " --- |".repeat(columns))];
+        lines.extend(rows.iter().map(|row| row_text(row)));
+        lines.join("\n")
+    }
+
+    /// ข้อความในช่องของ pipe table: บรรทัดเดียว (<br> -> `<br>`) และ escape `|`
+    fn table_cell(&self, cell: ElementRef) -> String {
+        collapse_lines(&self.inline_children(cell))
+            .replace('|', "\\|")
+            .replace('\n', "<br>")
+    }
+
+    /// ตารางในรูปรายการ: แถวละหนึ่งข้อ ช่องแรกเป็นข้อความของข้อ ช่องถัดไปเป็นข้อย่อย
+    /// (มีหัวตาราง -> "หัวคอลัมน์: ค่า")
+    fn table_as_list(&self, cells: &[Vec<ElementRef>], header: bool) -> String {
+        let (headings, body) = match cells.split_first() {
+            Some((first, rest)) if header => (
+                first
+                    .iter()
+                    .map(|c| collapse_lines(&self.inline_children(*c)).replace('\n', " "))
+                    .collect(),
+                rest,
+            ),
+            _ => (Vec::new(), cells),
+        };
+        let mut items = Vec::new();
+        for row in body {
+            let mut column = 0;
+            let mut fields = Vec::new();
+            for cell in row {
+                let content = self.blocks(cell.children()).join("\n\n");
+                if !content.is_empty() {
+                    let field = match headings.get(column).filter(|h: &&String| !h.is_empty()) {
+                        // ค่าหลายบรรทัด (เช่นรายการ) ขึ้นบรรทัดใหม่ใต้หัวคอลัมน์
+                        Some(heading) if content.contains('\n') => format!("{}:\n{}", heading, content),
+                        Some(heading) => format!("{}: {}", heading, content),
+                        None => content,
+                    };
+                    fields.push(field);
+                }
+                column += span(*cell, "colspan");
+            }
+            let Some((first, rest)) = fields.split_first() else {
+                continue;
+            };
+            let mut content = first.clone();
+            for field in rest {
+                content.push('\n');
+                content.push_str(&list_item("-", field));
+            }
+            items.push(list_item("-", &content));
         }
+        items.join("\n")
     }
-    result
-}
 
-fn convert_strong(html: &str) -> String {
-    replace_tag_pair(html, "<strong>", "</strong>", "**")
-        .replace("<b>", "**")
-        .replace("</b>", "**")
-}
+    /// แปลง <ul>/<ol> เป็นรายการ
+    /// - รายการซ้อนเยื้องตามความกว้างของ marker ของข้อที่ห่ออยู่ ("- " = 2, "10. " = 4)
+    /// - เลขเริ่มใหม่ทุกรายการ ตาม `<ol start>`, `reversed` และ `<li value>`
+    /// - ข้อที่มีหลายย่อหน้าทำให้ทั้งรายการเป็น loose list (คั่นข้อด้วยบรรทัดว่าง)
+    fn list(&self, el: ElementRef) -> String {
+        let ordered = el.value().name() == "ol";
+        let mut items: Vec<ListItem> = Vec::new();
+        for child in el.children() {
+            match ElementRef::wrap(child) {
+                Some(li) if li.value().name() == "li" => {
+                    let value = li.value().attr("value").and_then(|v| v.trim().parse().ok());
+                    let item = self.list_item_content(li.children(), value);
+                    if !item.content.trim().is_empty() {
+                        items.push(item);
+                    }
+                }
+                // <ul> ที่ถูกใส่ใน <ul> ตรง ๆ (markup ผิดแต่พบบ่อย) -> รายการซ้อนของข้อก่อนหน้า
+                Some(nested) if matches!(nested.value().name(), "ul" | "ol") && !items.is_empty() => {
+                    let nested = self.list(nested);
+                    if let (Some(last), false) = (items.last_mut(), nested.is_empty()) {
+                        last.content.push('\n');
+                        last.content.push_str(&nested);
+                    }
+                }
+                _ => {
+                    let item = self.list_item_content(std::iter::once(child), None);
+                    if !item.content.trim().is_empty() {
+                        items.push(item);
+                    }
+                }
+            }
+        }
 
-fn convert_em(html: &str) -> String {
-    replace_tag_pair(html, "<em>", "</em>", "*")
-        .replace("<i>", "*")
-        .replace("</i>", "*")
-}
+        let reversed = ordered && el.value().attr("reversed").is_some();
+        let mut number: i64 = el
+            .value()
+            .attr("start")
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(if reversed { items.len() as i64 } else { 1 });
+        let loose = items.iter().any(|item| item.loose);
+        let mut lines = Vec::with_capacity(items.len());
+        for item in &items {
+            let marker = if ordered {
+                number = item.value.unwrap_or(number);
+                // markdown ไม่รองรับเลขติดลบ
+                format!("{}.", number.max(0))
+            } else {
+                "-".to_string()
+            };
+            number += if reversed { -1 } else { 1 };
+            lines.push(list_item(&marker, &item.content));
+        }
+        lines.join(if loose { "\n\n" } else { "\n" })
+    }
 
-fn convert_lists(html: &str) -> String {
-    let mut result = html.to_string();
-    
-    // Unordered lists
-    while let Some(start) = result.find("<ul>") {
-        if let Some(end) = result[start..].find("</ul>") {
-            let content = &result[start + 4..start + end];
-            let items = convert_list_items(content, "- ");
-            result.replace_range(start..start + end + 5, &format!("\n\n{}\n\n", items));
-        } else {
-            break;
+    /// เนื้อหาของข้อหนึ่งข้อ: block ปกติคั่นด้วยบรรทัดว่าง รายการซ้อนต่อท้ายบรรทัดถัดไปเลย
+    /// (มีย่อหน้าเดียว + รายการซ้อน ยังเป็น tight list)
+    fn list_item_content<'a>(&self, nodes: impl Iterator<Item = NodeRef<'a, Node>>, value: Option<i64>) -> ListItem {
+        let mut parts: Vec<(String, bool)> = Vec::new();
+        let mut pending = Vec::new();
+        for node in nodes {
+            match ElementRef::wrap(node) {
+                Some(nested) if matches!(nested.value().name(), "ul" | "ol") => {
+                    parts.extend(self.blocks(pending.drain(..)).into_iter().map(|b| (b, false)));
+                    let nested = self.list(nested);
+                    if !nested.is_empty() {
+                        parts.push((nested, true));
+                    }
+                }
+                _ => pending.push(node),
+            }
+        }
+        parts.extend(self.blocks(pending.into_iter()).into_iter().map(|b| (b, false)));
+
+        let loose = parts.iter().filter(|(_, is_list)| !is_list).count() > 1;
+        let mut content = String::new();
+        for (i, (part, is_list)) in parts.iter().enumerate() {
+            if i > 0 {
+                content.push_str(if *is_list && !loose { "\n" } else { "\n\n" });
+            }
+            content.push_str(part);
         }
+        ListItem { content, loose, value }
     }
-    
-    // Ordered lists
-    let mut ol_index = 1;
-    while let Some(start) = result.find("<ol>") {
-        if let Some(end) = result[start..].find("</ol>") {
-            let content = &result[start + 4..start + end];
-            let items = convert_list_items_ordered(content, &mut ol_index);
-            result.replace_range(start..start + end + 5, &format!("\n\n{}\n\n", items));
-        } else {
-            break;
+
+    /// แปลง node เป็นข้อความ inline
+    fn inline(&self, node: NodeRef<Node>) -> String {
+        match node.value() {
+            Node::Text(text) => escape_text(&collapse_whitespace(text)),
+            Node::Element(_) => match ElementRef::wrap(node) {
+                Some(el) => self.inline_element(el),
+                None => String::new(),
+            },
+            _ => String::new(),
+        }
+    }
+
+    fn inline_children(&self, el: ElementRef) -> String {
+        el.children().map(|child| self.inline(child)).collect()
+    }
+
+    fn inline_element(&self, el: ElementRef) -> String {
+        let name = el.value().name();
+        if SKIPPED_TAGS.contains(&name) {
+            return String::new();
+        }
+        match name {
+            "br" => "\n".to_string(),
+            "a" => {
+                let inner = self.inline_children(el);
+                let text = collapse_lines(&inner).replace('\n', " ");
+                let href = el.value().attr("href").map(str::trim).unwrap_or_default();
+                if text.is_empty() || href.is_empty() || href.starts_with("javascript:") {
+                    inner
+                } else {
+                    wrap_spaces(&inner, &format!("[{}]({})", text, self.link_target(href)))
+                }
+            }
+            "img" => {
+                // lazy loading มักใส่รูปจริงไว้ใน data-src และ src เป็น placeholder
+                let src = [el.value().attr("src"), el.value().attr("data-src")]
+                    .into_iter()
+                    .flatten()
+                    .map(str::trim)
+                    .find(|s| !s.is_empty() && !s.starts_with("data:"))
+                    .unwrap_or_default();
+                if src.is_empty() {
+                    return String::new();
+                }
+                let alt = escape_text(&collapse_whitespace(el.value().attr("alt").unwrap_or_default()));
+                format!("![{}]({})", alt.trim(), self.resolve(src))
+            }
+            "strong" | "b" => wrap_inline(&self.inline_children(el), "**"),
+            "em" | "i" => wrap_inline(&self.inline_children(el), "*"),
+            "del" | "s" | "strike" => wrap_inline(&self.inline_children(el), "~~"),
+            "code" | "kbd" | "samp" | "tt" => code_span(&collapse_whitespace(&el.text().collect::<String>())),
+            // block ที่ซ้อนอยู่ใน inline (เช่น <a><div>..</div></a>) คั่นด้วยช่องว่าง
+            _ if BLOCK_TAGS.contains(&name) => format!(" {} ", self.inline_children(el)),
+            _ => self.inline_children(el),
         }
     }
-    
-    result
 }
 
-fn convert_list_items(html: &str, prefix: &str) -> String {
-    let mut result = String::new();
-    let mut remaining = html;
-    
-    while let Some(start) = remaining.find("<li>") {
-        if let Some(end) = remaining[start..].find("</li>") {
-            let content = &remaining[start + 4..start + end];
-            result.push_str(&format!("{}{}\n", prefix, strip_html_tags(content).trim()));
-            remaining = &remaining[start + end + 5..];
-        } else {
-            break;
+/// prefix ของ class ที่บอกภาษาของ code block (highlight.js, Prism, Pygments/Sphinx)
+const LANGUAGE_CLASS_PREFIXES: &[&str] = &["language-", "lang-", "highlight-source-", "highlight-"];
+
+/// แปลง <pre> เป็น fenced code block
+/// - เก็บ whitespace ตามต้นฉบับทุกตัว (ตัดเฉพาะ newline ตัวสุดท้ายที่ fence ปิดเติมให้อยู่แล้ว)
+/// - fence ยาวกว่า backtick ที่ติดกันยาวที่สุดในโค้ด (อย่างน้อย 3 ตัว)
+/// - ภาษาจาก class/data-lang ของ <pre>, <code> ข้างใน หรือกล่องที่ห่อ <pre> อยู่
+fn code_block(pre: ElementRef) -> Option<String> {
+    let mut code = String::new();
+    preformatted_text(*pre, &mut code);
+    let code = code
+        .strip_suffix("\r\n")
+        .or_else(|| code.strip_suffix('\n'))
+        .unwrap_or(&code);
+    if code.trim().is_empty() {
+        return None;
+    }
+
+    let longest = code.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat((longest + 1).max(3));
+    let language = code_language(pre).unwrap_or_default();
+    Some(format!("{}{}\n{}\n{}", fence, language, code, fence))
+}
+
+/// ข้อความใน <pre> ตามต้นฉบับ (<br> จาก syntax highlighter บางตัว = ขึ้นบรรทัดใหม่)
+fn preformatted_text(node: NodeRef<Node>, out: &mut String) {
+    for child in node.children() {
+        match child.value() {
+            Node::Text(text) => out.push_str(text),
+            Node::Element(el) if el.name() == "br" => out.push('\n'),
+            Node::Element(el) if SKIPPED_TAGS.contains(&el.name()) => {}
+            Node::Element(_) => preformatted_text(child, out),
+            _ => {}
         }
     }
-    
-    result
 }
 
-fn convert_list_items_ordered(html: &str, start_index: &mut usize) -> String {
-    let mut result = String::new();
-    let mut remaining = html;
-    
-    while let Some(start) = remaining.find("<li>") {
-        if let Some(end) = remaining[start..].find("</li>") {
-            let content = &remaining[start + 4..start + end];
-            result.push_str(&format!("{}. {}\n", start_index, strip_html_tags(content).trim()));
-            *start_index += 1;
-            remaining = &remaining[start + end + 5..];
-        } else {
-            break;
+/// หาภาษาจาก <pre> -> <code> ลูก -> กล่องที่ห่อ (สูงสุด 2 ชั้น เช่น Sphinx `<div class="highlight-python"><div class="highlight"><pre>`)
+fn code_language(pre: ElementRef) -> Option<String> {
+    let code = pre.child_elements().find(|c| c.value().name() == "code");
+    let wrappers = pre.ancestors().filter_map(ElementRef::wrap).take(2);
+    std::iter::once(pre)
+        .chain(code)
+        .chain(wrappers)
+        .find_map(element_language)
+}
+
+fn element_language(el: ElementRef) -> Option<String> {
+    let element = el.value();
+    let declared = element.attr("data-lang").or_else(|| element.attr("data-language"));
+    let from_class = || {
+        element.classes().find_map(|class| {
+            LANGUAGE_CLASS_PREFIXES
+                .iter()
+                .find_map(|prefix| class.strip_prefix(prefix))
+        })
+    };
+    let language: String = declared
+        .or_else(from_class)?
+        .trim()
+        .to_ascii_lowercase()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '#' | '-' | '_' | '.'))
+        .collect();
+    // "default"/"none" ของ Pygments ไม่ใช่ชื่อภาษา
+    (!language.is_empty() && !matches!(language.as_str(), "default" | "none")).then_some(language)
+}
+
+/// ข้อในรายการที่แปลงแล้ว ก่อนใส่ marker
+struct ListItem {
+    content: String,
+    /// มีหลายย่อหน้า (หรือ block อื่น) ในข้อเดียว
+    loose: bool,
+    /// เลขจาก `<li value>`
+    value: Option<i64>,
+}
+
+/// ข้อในรายการ: marker นำหน้าบรรทัดแรก บรรทัดถัดไปเยื้องตามความกว้างของ marker
+fn list_item(marker: &str, content: &str) -> String {
+    let indent = " ".repeat(marker.len() + 1);
+    let mut item = format!("{} ", marker);
+    for (i, line) in content.lines().enumerate() {
+        if i > 0 {
+            item.push('\n');
+            if !line.is_empty() {
+                item.push_str(&indent);
+            }
         }
+        item.push_str(line);
     }
-    
-    result
+    item
 }
 
-fn convert_blockquotes(html: &str) -> String {
-    let mut result = html.to_string();
-    while let Some(start) = result.find("<blockquote>") {
-        if let Some(end) = result[start..].find("</blockquote>") {
-            let content = &result[start + 12..start + end];
-            let lines: Vec<_> = strip_html_tags(content)
-                .lines()
-                .map(|l| format!("> {}", l.trim()))
-                .collect();
-            result.replace_range(start..start + end + 13, &format!("\n\n{}\n\n", lines.join("\n")));
-        } else {
-            break;
+/// แถวของตาราง (ใน <thead>/<tbody>/<tfoot> หรือเป็นลูกของ <table> โดยตรง) ไม่รวมตารางซ้อน
+fn table_rows(table: ElementRef) -> Vec<ElementRef> {
+    let mut rows = Vec::new();
+    for child in table.child_elements() {
+        match child.value().name() {
+            "tr" => rows.push(child),
+            "thead" | "tbody" | "tfoot" => rows.extend(child.child_elements().filter(|r| r.value().name() == "tr")),
+            _ => {}
         }
     }
-    result
+    // <thead> ขึ้นก่อนเสมอ ถึงจะเขียนไว้หลัง <tbody>
+    rows.sort_by_key(|row| {
+        row.parent()
+            .and_then(ElementRef::wrap)
+            .is_none_or(|p| p.value().name() != "thead")
+    });
+    rows
 }
 
-fn convert_code(html: &str) -> String {
-    replace_tag_pair(html, "<code>", "</code>", "`")
+/// ค่า colspan/rowspan ของช่อง (ไม่ระบุหรืออ่านไม่ได้ = 1)
+fn span(cell: ElementRef, attr: &str) -> usize {
+    cell.value()
+        .attr(attr)
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .unwrap_or(1)
+        .max(1)
 }
 
-fn replace_tag_pair(html: &str, open: &str, close: &str, markdown: &str) -> String {
-    let mut result = html.to_string();
-    while let Some(start) = result.find(open) {
-        if let Some(end) = result[start..].find(close) {
-            let content = &result[start + open.len()..start + end];
-            let replacement = format!("{}{}{}", markdown, content, markdown);
-            result.replace_range(start..start + end + close.len(), &replacement);
-        } else {
-            break;
+/// ช่องตารางมี block ที่ pipe table แสดงไม่ได้ (รายการ, code block, ตารางซ้อน, หลายย่อหน้า)
+fn has_block_content(cell: ElementRef) -> bool {
+    let nested = cell.descendent_elements().any(|d| {
+        matches!(
+            d.value().name(),
+            "table" | "ul" | "ol" | "dl" | "pre" | "blockquote" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "hr"
+        )
+    });
+    let paragraphs = cell
+        .child_elements()
+        .filter(|c| matches!(c.value().name(), "p" | "div"))
+        .count();
+    nested || paragraphs > 1
+}
+
+/// HTML ดิบของ element เป็น HTML block ของ markdown (บรรทัดว่างจะจบ block จึงตัดทิ้ง)
+fn raw_html_block(html: &str) -> String {
+    html
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// เพิ่มข้อความ inline ที่สะสมไว้เป็นย่อหน้า (ถ้ามีเนื้อหา) คืน true ถ้าเพิ่ม
+fn push_paragraph(blocks: &mut Vec<String>, inline: &str) -> bool {
+    let paragraph = collapse_lines(inline);
+    if paragraph.is_empty() {
+        return false;
+    }
+    blocks.push(paragraph.split('\n').map(escape_line_start).collect::<Vec<_>>().join("\n"));
+    true
+}
+
+/// escape ตัวอักษรในข้อความที่ markdown จะตีความเป็น markup
+/// (entity เช่น &amp; &nbsp; &#8217; html5ever decode ให้แล้วตอน parse ทั้งใน text และ attribute)
+/// - `\` `*` `[` `` ` `` เสมอ
+/// - `_` เฉพาะที่ติดขอบคำ (snake_case ไม่เป็นตัวเอียงใน markdown จึงไม่ต้อง escape)
+/// - `<` ที่จะกลายเป็น HTML tag และ `&` ที่จะกลายเป็น entity อีกรอบ (เช่นข้อความ "&copy;" ที่มาจาก &amp;copy;)
+fn escape_text(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    for (i, &c) in chars.iter().enumerate() {
+        let prev = i.checked_sub(1).map(|j| chars[j]);
+        let next = chars.get(i + 1).copied();
+        let escape = match c {
+            '\\' | '*' | '[' | '`' => true,
+            '_' => !prev.is_some_and(char::is_alphanumeric) || !next.is_some_and(char::is_alphanumeric),
+            '<' => next.is_some_and(|n| n.is_ascii_alphabetic() || matches!(n, '/' | '!' | '?')),
+            '&' => looks_like_entity(&chars[i + 1..]),
+            _ => false,
+        };
+        if escape {
+            out.push('\\');
         }
+        out.push(c);
     }
-    result
+    out
 }
 
-fn strip_html_tags(html: &str) -> String {
-    let mut result = String::new();
-    let mut in_tag = false;
-    
-    for ch in html.chars() {
-        match ch {
-            '<' => in_tag = true,
-            '>' => in_tag = false,
-            _ if !in_tag => result.push(ch),
-            _ => {}
+/// ตัวอักษรหลัง `&` เป็นรูป entity (`name;` หรือ `#123;`)
+fn looks_like_entity(rest: &[char]) -> bool {
+    let len = rest.iter().take_while(|c| c.is_ascii_alphanumeric() || **c == '#').count();
+    len > 0 && rest.get(len) == Some(&';')
+}
+
+/// escape ต้นบรรทัดของย่อหน้าที่ markdown จะตีความเป็น block
+/// (heading `#`, blockquote `>`, รายการ `- ` `+ ` `1. `, เส้นคั่น/setext `---` `===`, code fence `~~~`)
+fn escape_line_start(line: &str) -> String {
+    let mut chars = line.chars();
+    let first = chars.next();
+    let rest = chars.as_str();
+    let block_marker = match first {
+        Some('#' | '>') => true,
+        Some('-' | '+') => rest.is_empty() || rest.starts_with(' ') || rest.chars().all(|c| c == '-' || c == ' '),
+        Some('=') => rest.chars().all(|c| c == '=' || c == ' '),
+        Some('~') => rest.starts_with("~~"),
+        _ => false,
+    };
+    if block_marker {
+        return format!("\\{}", line);
+    }
+
+    // รายการแบบตัวเลข: "2024. " หรือ "1) " -> "2024\. "
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if (1..=9).contains(&digits) {
+        let after = &line[digits..];
+        if let Some(delimiter @ ('.' | ')')) = after.chars().next()
+            && (after.len() == 1 || after[1..].starts_with(' '))
+        {
+            return format!("{}\\{}{}", &line[..digits], delimiter, &after[1..]);
         }
     }
-    
-    result
+    line.to_string()
 }
 
-fn clean_whitespace(text: &str) -> String {
-    let mut result = String::new();
-    let mut prev_newline = false;
-    
-    for line in text.lines() {
-        let trimmed = line.trim();
-        if trimmed.is_empty() {
-            if !prev_newline {
-                result.push('\n');
-                prev_newline = true;
+/// ยุบ whitespace ต่อเนื่องเป็นช่องว่างเดียว (ไม่ยุบ &nbsp;)
+fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut prev_space = false;
+    for ch in text.chars() {
+        if ch.is_ascii_whitespace() {
+            if !prev_space {
+                out.push(' ');
             }
+            prev_space = true;
         } else {
-            result.push_str(trimmed);
-            result.push('\n');
-            prev_newline = false;
+            out.push(ch);
+            prev_space = false;
         }
     }
-    
-    result.trim().to_string()
+    out
+}
+
+/// ตัดช่องว่างซ้ำ/หัวท้ายของแต่ละบรรทัด (บรรทัดมาจาก <br>) และตัดบรรทัดว่างหัวท้าย
+fn collapse_lines(text: &str) -> String {
+    let lines: Vec<String> = text
+        .split('\n')
+        .map(|line| line.split(' ').filter(|w| !w.is_empty()).collect::<Vec<_>>().join(" "))
+        .collect();
+    let start = lines.iter().position(|l| !l.is_empty()).unwrap_or(lines.len());
+    let end = lines.iter().rposition(|l| !l.is_empty()).map_or(start, |i| i + 1);
+    lines[start..end].join("\n")
+}
+
+/// ครอบข้อความด้วย marker เช่น ** โดยย้ายช่องว่างหัวท้ายออกไปนอก marker
+/// (markdown ไม่นับ "** text **" เป็นตัวหนา)
+fn wrap_inline(text: &str, marker: &str) -> String {
+    let trimmed = text.trim_matches(' ');
+    if trimmed.trim().is_empty() {
+        return text.to_string();
+    }
+    wrap_spaces(text, &format!("{}{}{}", marker, trimmed, marker))
 }
 
-fn extract_attr(tag: &str, attr: &str) -> String {
-    let pattern = format!("{}=\"", attr);
-    if let Some(start) = tag.find(&pattern) {
-        let value_start = start + pattern.len();
-        if let Some(end) = tag[value_start..].find('"') {
-            return tag[value_start..value_start + end].to_string();
+/// ใส่ช่องว่างหัวท้ายของ original กลับรอบ markdown ที่แปลงแล้ว
+fn wrap_spaces(original: &str, markdown: &str) -> String {
+    let leading = if original.starts_with(' ') { " " } else { "" };
+    let trailing = if original.ends_with(' ') { " " } else { "" };
+    format!("{}{}{}", leading, markdown, trailing)
+}
+
+/// inline code ใช้ backtick มากกว่าที่มีติดกันในข้อความหนึ่งตัว
+fn code_span(text: &str) -> String {
+    let code = text.trim();
+    if code.is_empty() {
+        return String::new();
+    }
+    let longest = code.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest + 1);
+    let pad = if code.starts_with('`') || code.ends_with('`') { " " } else { "" };
+    format!("{}{}{}{}{}", fence, pad, code, pad, fence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn html_to_markdown(url: &str, html: &str) -> String {
+        convert_html(url, html, &ConvertOptions::default())
+    }
+
+    #[test]
+    fn blocks_and_inline_markup() {
+        let html = "<html><head><title>t</title><script>x()</script></head><body>\
+<h2>Intro <em>here</em></h2><p>Some <strong>bold</strong> and <a href=\"/docs\">docs</a>.</p><hr><p>end</p></body></html>";
+        assert_eq!(
+            html_to_markdown("https://example.com/a/", html),
+            "## Intro *here*\n\nSome **bold** and [docs](https://example.com/docs).\n\n---\n\nend"
+        );
+    }
+
+    #[test]
+    fn empty_page_reports_no_content() {
+        assert_eq!(
+            html_to_markdown("https://example.com/", "<html><body><script>x()</script></body></html>"),
+            "# https://example.com/\n\nNo content extracted.\n"
+        );
+    }
+
+    fn convert_with(html: &str, table_fallback: TableFallback) -> String {
+        let options = ConvertOptions { table_fallback, ..ConvertOptions::default() };
+        convert_html("https://example.com/docs/", html, &options)
+    }
+
+    #[test]
+    fn table_becomes_pipe_table_with_thead_header() {
+        let html = "<table><tbody><tr><td>a|b</td><td>1<br>2</td></tr></tbody>\
+<thead><tr><td>Name</td><td>Value</td></tr></thead></table>";
+        assert_eq!(
+            html_to_markdown("https://example.com/", html),
+            "| Name | Value |\n| --- | --- |\n| a\\|b | 1<br>2 |"
+        );
+    }
+
+    #[test]
+    fn table_without_header_gets_empty_header_row() {
+        let html = "<table><tr><td>a</td><td>b</td></tr><tr><td>c</td></tr></table>";
+        assert_eq!(
+            html_to_markdown("https://example.com/", html),
+            "|  |  |\n| --- | --- |\n| a | b |\n| c |  |"
+        );
+        let html = "<table><tr><th>H1</th><th>H2</th></tr><tr><td>a</td><td>b</td></tr></table>";
+        assert_eq!(html_to_markdown("https://example.com/", html), "| H1 | H2 |\n| --- | --- |\n| a | b |");
+    }
+
+    #[test]
+    fn complex_table_falls_back_to_resolved_html() {
+        let html = "<table><tr><th colspan=\"2\">Head</th></tr>\n\n<tr><td><a href=\"page\">link</a></td><td>2</td></tr></table>";
+        let markdown = convert_with(html, TableFallback::Html);
+        assert!(markdown.starts_with("<table>"), "{}", markdown);
+        assert!(markdown.contains(r#"<a href="https://example.com/docs/page">link</a>"#), "{}", markdown);
+        assert!(!markdown.lines().any(|line| line.trim().is_empty()), "{}", markdown);
+    }
+
+    #[test]
+    fn complex_table_falls_back_to_list() {
+        let html = "<table><tr><th>Name</th><th>Notes</th></tr>\
+<tr><td>a</td><td><ul><li>x</li><li>y</li></ul></td></tr><tr><td>b</td><td>plain</td></tr></table>";
+        assert_eq!(
+            convert_with(html, TableFallback::List),
+            "- Name: a\n  - Notes:\n    - x\n    - y\n- Name: b\n  - Notes: plain"
+        );
+    }
+
+    #[test]
+    fn code_block_fence_is_longer_than_backticks_in_code() {
+        let html = "<pre><code>a ``` b\n````c</code></pre>";
+        assert_eq!(html_to_markdown("https://example.com/", html), "`````\na ``` b\n````c\n`````");
+        let html = "<pre>plain</pre>";
+        assert_eq!(html_to_markdown("https://example.com/", html), "```\nplain\n```");
+    }
+
+    #[test]
+    fn code_block_keeps_whitespace() {
+        let html = "<pre>\n  fn main() {\n\t<span>let x = 1;</span>\n\n  }<br>// &lt;end&gt;\n</pre>";
+        assert_eq!(
+            html_to_markdown("https://example.com/", html),
+            "```\n  fn main() {\n\tlet x = 1;\n\n  }\n// <end>\n```"
+        );
+    }
+
+    #[test]
+    fn code_block_language_from_class_and_data_lang() {
+        let cases = [
+            ("<pre><code class=\"hljs language-rust\">x</code></pre>", "rust"),
+            ("<pre class=\"prettyprint lang-Python\">x</pre>", "python"),
+            ("<div class=\"highlight-javascript notranslate\"><div class=\"highlight\"><pre>x</pre></div></div>", "javascript"),
+            ("<pre data-lang=\"c++\">x</pre>", "c++"),
+            ("<div class=\"highlight-default\"><pre>x</pre></div>", ""),
+        ];
+        for (html, language) in cases {
+            assert_eq!(
+                html_to_markdown("https://example.com/", html),
+                format!("```{}\nx\n```", language),
+                "{}",
+                html
+            );
         }
     }
-    String::new()
-}
\ No newline at end of file
+
+    #[test]
+    fn ordered_list_numbering() {
+        let md = |html: &str| html_to_markdown("https://example.com/", html);
+        assert_eq!(md("<ol start=\"3\"><li>a</li><li>b</li></ol>"), "3. a\n4. b");
+        assert_eq!(md("<ol reversed><li>a</li><li>b</li><li>c</li></ol>"), "3. a\n2. b\n1. c");
+        assert_eq!(md("<ol reversed start=\"10\"><li>a</li><li>b</li></ol>"), "10. a\n9. b");
+        assert_eq!(md("<ol><li>a</li><li value=\"10\">b</li><li>c</li></ol>"), "1. a\n10. b\n11. c");
+    }
+
+    #[test]
+    fn each_list_restarts_numbering() {
+        let md = |html: &str| html_to_markdown("https://example.com/", html);
+        assert_eq!(md("<ol><li>a</li></ol><p>x</p><ol><li>b</li></ol>"), "1. a\n\nx\n\n1. b");
+        assert_eq!(md("<ol><li>a</li></ol><ol><li>b</li></ol>"), "1. a\n\n<!-- -->\n\n1. b");
+    }
+
+    #[test]
+    fn nested_list_indents_by_marker_width() {
+        let md = |html: &str| html_to_markdown("https://example.com/", html);
+        assert_eq!(
+            md("<ul><li>a<ol><li>b</li><li>c</li></ol></li><li>d</li></ul>"),
+            "- a\n  1. b\n  2. c\n- d"
+        );
+        assert_eq!(md("<ol start=\"10\"><li>a<ul><li>b</li></ul></li></ol>"), "10. a\n    - b");
+        // <ul> ที่อยู่ใน <ul> ตรง ๆ เป็นรายการซ้อนของข้อก่อนหน้า
+        assert_eq!(md("<ul><li>a</li><ul><li>b</li></ul></ul>"), "- a\n  - b");
+    }
+
+    #[test]
+    fn multi_paragraph_item_makes_list_loose() {
+        let md = |html: &str| html_to_markdown("https://example.com/", html);
+        assert_eq!(
+            md("<ul><li><p>one</p><p>two</p></li><li>b</li></ul>"),
+            "- one\n\n  two\n\n- b"
+        );
+    }
+
+    #[test]
+    fn decoded_entities_stay_literal_text() {
+        let md = |html: &str| html_to_markdown("https://example.com/", html);
+        assert_eq!(md("<p>&amp;copy; &copy; &lt;b&gt; a &amp; b</p>"), r"\&copy; © \<b> a & b");
+        assert_eq!(md("<p>2*3 [x] `y` a\\b</p>"), r"2\*3 \[x] \`y\` a\\b");
+    }
+
+    #[test]
+    fn underscore_escaped_only_at_word_edges() {
+        let md = |html: &str| html_to_markdown("https://example.com/", html);
+        assert_eq!(md("<p>snake_case _under_ a_ __init__</p>"), r"snake_case \_under\_ a\_ \_\_init\_\_");
+    }
+
+    #[test]
+    fn block_markers_escaped_at_line_start() {
+        let md = |html: &str| html_to_markdown("https://example.com/", html);
+        assert_eq!(md("<p># not a heading</p>"), r"\# not a heading");
+        assert_eq!(md("<p>1. not a list</p>"), r"1\. not a list");
+        assert_eq!(md("<p>- not a list</p>"), r"\- not a list");
+        assert_eq!(md("<p>a<br>2024) x<br>&gt; y<br>---</p>"), "a\n2024\\) x\n\\> y\n\\---");
+        // กลางบรรทัดหรือไม่มีช่องว่างตามหลัง ไม่ต้อง escape
+        assert_eq!(md("<p>a # b 1.5 -x</p>"), "a # b 1.5 -x");
+    }
+
+    #[test]
+    fn uppercase_attributed_and_malformed_markup() {
+        let md = |html: &str| html_to_markdown("https://example.com/", html);
+        assert_eq!(md("<P CLASS=x>Hello</P><P ID='y'>World"), "Hello\n\nWorld");
+        assert_eq!(md("<p><A\nHREF=\"/x\"\n  title=t>link</A></p>"), "[link](https://example.com/x)");
+        assert_eq!(md("<ul><li>one<li>two</ul><p>a<p>b"), "- one\n- two\n\na\n\nb");
+        // html5ever จัดให้เป็น <b>bold <i>both</i></b><i> italic</i> แบบเดียวกับ browser
+        assert_eq!(md("<p><b>bold <i>both</b> italic</i></p>"), "**bold *both*** *italic*");
+    }
+}
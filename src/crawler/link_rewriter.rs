@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::canonical::UrlNormalizer;
+use super::front_matter::{split_front_matter, update_content_hash};
+use super::html_fetcher::FetchedPage;
+use super::markdown_writer::markdown_path;
+
+/// ปลายทางของ link ไปหน้าอื่นในเว็บเดียวกัน
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InternalLinks {
+    /// URL เต็มตามที่ converter resolve ไว้
+    Absolute,
+    /// หลัง crawl เสร็จ เขียน link ไปหน้าที่บันทึกไว้ใหม่ให้ชี้ไฟล์ .md (หน้าที่ไม่ได้ crawl คง URL เต็ม)
+    Local,
+}
+
+impl InternalLinks {
+    pub fn from_name(s: &str) -> Self {
+        match s {
+            "local" => InternalLinks::Local,
+            _ => InternalLinks::Absolute,
+        }
+    }
+}
+
+/// URL -> ไฟล์ markdown ที่ write_markdown_file บันทึกไว้ใน crawl นี้
+/// key เป็น URL ที่ normalize แล้ว จึงจับคู่ได้แม้ link ต่างกันที่ tracking params/ลำดับ query/index.html
+/// และเป็นที่จองชื่อไฟล์ ไม่ให้หน้าต่าง URL ที่ slug เดียวกัน (/learn กับ /ja/learn) เขียนทับกัน
+pub struct LinkMap {
+    normalizer: UrlNormalizer,
+    files: HashMap<String, PathBuf>,
+    /// ไฟล์ที่จองแล้ว -> URL (normalize แล้ว) ของหน้าที่เป็นเจ้าของ
+    owners: HashMap<PathBuf, String>,
+    /// หน้าที่ข้ามเพราะซ้ำ -> URL ของหน้าที่บันทึกแทน
+    aliases: HashMap<String, String>,
+}
+
+impl LinkMap {
+    pub fn new(normalizer: UrlNormalizer) -> Self {
+        Self {
+            normalizer,
+            files: HashMap::new(),
+            owners: HashMap::new(),
+            aliases: HashMap::new(),
+        }
+    }
+
+    /// จองไฟล์ให้หน้านี้ก่อนเขียน ถ้า output/<slug>.md เป็นของหน้าอื่นแล้วใช้ <slug>-2.md, <slug>-3.md, ...
+    pub fn reserve(&mut self, page: &FetchedPage) -> PathBuf {
+        let owner = self.normalizer.normalize(&page.final_url);
+        let mut n = 1;
+        loop {
+            let path = markdown_path(page, n);
+            match self.owners.get(&path) {
+                Some(existing) if *existing != owner => n += 1,
+                Some(_) => return path,
+                None => {
+                    if n > 1 {
+                        println!(
+                            "[link_rewriter] {} ใช้ชื่อไฟล์ซ้ำกับหน้าอื่น บันทึกเป็น {} แทน",
+                            page.final_url,
+                            path.display()
+                        );
+                    }
+                    self.owners.insert(path.clone(), owner);
+                    return path;
+                }
+            }
+        }
+    }
+
+    /// บันทึกว่าหน้านี้ (ทั้ง URL ที่ขอและ URL หลัง redirect) อยู่ในไฟล์ path
+    pub fn record(&mut self, page: &FetchedPage, path: &Path) {
+        for url in [&page.url, &page.final_url] {
+            self.files.insert(self.normalizer.normalize(url), path.to_path_buf());
+        }
+    }
+
+    /// หน้าที่ข้ามเพราะซ้ำกับ same_as ให้ link ไปไฟล์ของ same_as
+    pub fn alias(&mut self, url: &str, same_as: &str) {
+        let key = self.normalizer.normalize(url);
+        if !self.files.contains_key(&key) {
+            self.aliases.insert(key, self.normalizer.normalize(same_as));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// ไฟล์ของ URL (ไม่ได้ crawl = None)
+    fn file_for(&self, url: &str) -> Option<&PathBuf> {
+        let key = self.normalizer.normalize(url);
+        self.files
+            .get(&key)
+            .or_else(|| self.aliases.get(&key).and_then(|target| self.files.get(target)))
+    }
+
+    /// เขียน link ในทุกไฟล์ที่บันทึกไว้ใหม่ คืนจำนวน link ที่เปลี่ยน
+    pub fn rewrite_all(&self) -> Result<usize, Box<dyn Error>> {
+        let mut paths: Vec<&PathBuf> = self.files.values().collect();
+        paths.sort();
+        paths.dedup();
+
+        let mut total = 0;
+        for path in paths {
+            let markdown = fs::read_to_string(path)?;
+            // ไม่แตะ front matter แต่ต้องคำนวณ content_hash ใหม่ให้ตรงกับเนื้อหาที่เปลี่ยน
+            let (front, body) = split_front_matter(&markdown);
+            let (rewritten, count) = self.rewrite_markdown(body, path);
+            if count > 0 {
+                match front {
+                    Some(front) => fs::write(path, update_content_hash(front, &rewritten) + &rewritten)?,
+                    None => fs::write(path, rewritten)?,
+                }
+                total += count;
+            }
+        }
+        Ok(total)
+    }
+
+    /// เปลี่ยนปลายทางของ `[text](url)` และ href/src ใน HTML ดิบ (ตาราง table_fallback = html)
+    /// ที่เป็นหน้าที่ crawl แล้วเป็น path ของไฟล์ .md (เก็บ #fragment ไว้)
+    /// ไม่แตะ code block และ link ที่ไม่ใช่ http(s)
+    fn rewrite_markdown(&self, markdown: &str, from: &Path) -> (String, usize) {
+        let mut out = String::with_capacity(markdown.len());
+        let mut count = 0;
+        let mut fence: Option<String> = None;
+
+        for line in markdown.split_inclusive('\n') {
+            let trimmed = line.trim_start();
+            let marker: String = trimmed.chars().take_while(|&c| c == '`' || c == '~').collect();
+            match &fence {
+                Some(open) => {
+                    if marker.starts_with(open.as_str()) && trimmed[marker.len()..].trim().is_empty() {
+                        fence = None;
+                    }
+                    out.push_str(line);
+                    continue;
+                }
+                None if marker.len() >= 3 && marker.chars().all(|c| c == marker.as_bytes()[0] as char) => {
+                    fence = Some(marker);
+                    out.push_str(line);
+                    continue;
+                }
+                None => {}
+            }
+            let line = self.rewrite_line(line, from, &mut count);
+            out.push_str(&self.rewrite_html_line(&line, from, &mut count));
+        }
+        (out, count)
+    }
+
+    fn rewrite_line(&self, line: &str, from: &Path, count: &mut usize) -> String {
+        let mut out = String::with_capacity(line.len());
+        let mut rest = line;
+        while let Some(pos) = rest.find("](") {
+            let (before, after) = rest.split_at(pos + 2);
+            out.push_str(before);
+            let end = destination_len(after);
+            let destination = &after[..end];
+            match self.local_target(destination, from) {
+                Some(local) => {
+                    out.push_str(&local);
+                    *count += 1;
+                }
+                None => out.push_str(destination),
+            }
+            rest = &after[end..];
+        }
+        out.push_str(rest);
+        out
+    }
+
+    /// href="..." / src="..." ภายใน tag HTML (ข้อความนอก tag ไม่แตะ)
+    /// ค่า attribute ถูก escape แบบ HTML จึง unescape ก่อนหาไฟล์แล้ว escape กลับ
+    fn rewrite_html_line(&self, line: &str, from: &Path, count: &mut usize) -> String {
+        let mut out = String::with_capacity(line.len());
+        let mut in_tag = false;
+        let mut rest = line;
+        while let Some(c) = rest.chars().next() {
+            let after = &rest[c.len_utf8()..];
+            if !in_tag {
+                in_tag = c == '<' && after.starts_with(|n: char| n.is_ascii_alphabetic());
+            } else if c == '>' {
+                in_tag = false;
+            } else if c == '"' {
+                // ค่า attribute อื่นคัดลอกทั้งก้อน ('>' ในค่าไม่ใช่การปิด tag)
+                let end = after.find('"').map_or(rest.len(), |i| i + 2);
+                out.push_str(&rest[..end]);
+                rest = &rest[end..];
+                continue;
+            } else if c.is_whitespace()
+                && let Some(attr) = ["href=\"", "src=\""].into_iter().find(|a| after.starts_with(a))
+                && let Some(len) = after[attr.len()..].find('"')
+            {
+                let start = c.len_utf8() + attr.len();
+                let value = &rest[start..start + len];
+                out.push_str(&rest[..start]);
+                match self.local_target(&unescape_attribute(value), from) {
+                    Some(local) => {
+                        out.push_str(&local.replace('&', "&amp;").replace('"', "&quot;"));
+                        *count += 1;
+                    }
+                    None => out.push_str(value),
+                }
+                out.push('"');
+                rest = &rest[start + len + 1..];
+                continue;
+            }
+            out.push(c);
+            rest = after;
+        }
+        out
+    }
+
+    /// path ของไฟล์ปลายทางเทียบกับไฟล์ from (ไฟล์ทั้งหมดอยู่ใน output/ ระดับเดียวกัน จึงเป็นชื่อไฟล์)
+    fn local_target(&self, destination: &str, from: &Path) -> Option<String> {
+        if !(destination.starts_with("http://") || destination.starts_with("https://")) {
+            return None;
+        }
+        let (url, fragment) = match destination.split_once('#') {
+            Some((url, fragment)) => (url, Some(fragment)),
+            None => (destination, None),
+        };
+        let file = self.file_for(url)?;
+        let relative = if file.parent() == from.parent() {
+            file.file_name()?.to_string_lossy().into_owned()
+        } else {
+            file.to_string_lossy().replace('\\', "/")
+        };
+        Some(match fragment {
+            Some(fragment) => format!("{}#{}", relative, fragment),
+            None => relative,
+        })
+    }
+}
+
+/// ค่า attribute ตามที่ html5ever serialize (escape แค่ &, " และ nbsp)
+fn unescape_attribute(value: &str) -> String {
+    value.replace("&quot;", "\"").replace("&nbsp;", "\u{a0}").replace("&amp;", "&")
+}
+
+/// ความยาวของปลายทาง link ต่อจาก "](" (ถึง ")" ที่ปิดวงเล็บของ link หรือช่องว่างก่อน title)
+fn destination_len(s: &str) -> usize {
+    let mut depth = 0usize;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return i,
+            ')' => depth -= 1,
+            c if c.is_whitespace() => return i,
+            _ => {}
+        }
+    }
+    s.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::config::AppConfig;
+    use crate::crawler::html_fetcher::FetchMode;
+    use std::time::Duration;
+
+    fn page(url: &str) -> FetchedPage {
+        let mut response = spider::page::Page::default();
+        response.final_redirect_destination = Some(url.to_string());
+        FetchedPage::from_spider_page(url, &response, FetchMode::HttpRequest, Duration::ZERO)
+    }
+
+    #[test]
+    fn reserve_gives_colliding_slugs_distinct_files() {
+        let mut map = LinkMap::new(UrlNormalizer::from_config(&AppConfig::default()));
+        let learn = map.reserve(&page("https://example.com/learn"));
+        let ja_learn = map.reserve(&page("https://example.com/ja/learn"));
+        assert_eq!(learn, PathBuf::from("output/learn.md"));
+        assert_eq!(ja_learn, PathBuf::from("output/learn-2.md"));
+        // หน้าเดิม (แม้ต่างกันที่ tracking params) ได้ไฟล์เดิม
+        assert_eq!(map.reserve(&page("https://example.com/learn?utm_source=x")), learn);
+        assert_eq!(map.reserve(&page("https://example.com/zh/learn")), PathBuf::from("output/learn-3.md"));
+    }
+
+    fn crawled(urls: &[&str]) -> LinkMap {
+        let mut map = LinkMap::new(UrlNormalizer::from_config(&AppConfig::default()));
+        for url in urls {
+            let page = page(url);
+            let path = map.reserve(&page);
+            map.record(&page, &path);
+        }
+        map
+    }
+
+    #[test]
+    fn rewrite_markdown_points_crawled_links_at_local_files() {
+        let map = crawled(&["https://example.com/learn", "https://example.com/tools"]);
+        let markdown = "See [Learn](https://example.com/learn#intro) and [Tools](https://example.com/tools?utm_source=x \"t\").\n\
+Not crawled [x](https://example.com/other), external [y](https://other.com/learn), ![img](https://example.com/learn)\n";
+        let (rewritten, count) = map.rewrite_markdown(markdown, Path::new("output/index.md"));
+        assert_eq!(
+            rewritten,
+            "See [Learn](learn.md#intro) and [Tools](tools.md \"t\").\n\
+Not crawled [x](https://example.com/other), external [y](https://other.com/learn), ![img](learn.md)\n"
+        );
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn rewrite_markdown_skips_fenced_code() {
+        let map = crawled(&["https://example.com/learn"]);
+        // ```` ปิด ``` ได้ แต่ ``` ไม่ปิด ~~~
+        let markdown = "```md\n[a](https://example.com/learn)\n````\n~~~\n```\n[b](https://example.com/learn)\n~~~\n[c](https://example.com/learn)\n";
+        let (rewritten, count) = map.rewrite_markdown(markdown, Path::new("output/index.md"));
+        assert_eq!(
+            rewritten,
+            "```md\n[a](https://example.com/learn)\n````\n~~~\n```\n[b](https://example.com/learn)\n~~~\n[c](learn.md)\n"
+        );
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn rewrite_html_line_changes_only_attributes_inside_tags() {
+        let map = crawled(&["https://example.com/learn?a=1&b=2"]);
+        let mut count = 0;
+        let line = "<td><a title=\"x > y\" href=\"https://example.com/learn?a=1&amp;b=2#top\">href=\"https://example.com/learn?a=1&amp;b=2\"</a>\
+<img src=\"https://other.com/learn\"></td>\n";
+        assert_eq!(
+            map.rewrite_html_line(line, Path::new("output/index.md"), &mut count),
+            "<td><a title=\"x > y\" href=\"learn.md#top\">href=\"https://example.com/learn?a=1&amp;b=2\"</a>\
+<img src=\"https://other.com/learn\"></td>\n"
+        );
+        assert_eq!(count, 1);
+    }
+}
@@ -0,0 +1,338 @@
+use ego_tree::NodeId;
+use scraper::{ElementRef, Html, Selector};
+use std::collections::HashMap;
+
+/// element ที่เป็นส่วนประกอบของเว็บ ไม่ใช่เนื้อหา (ลบทิ้งทุกที่)
+const BOILERPLATE_TAGS: &[&str] = &["nav", "aside", "dialog", "select", "button"];
+
+/// header/footer ลบเฉพาะที่เป็นของทั้งหน้า (header ใน <article> มักมีหัวเรื่องของบทความ)
+const PAGE_CHROME_TAGS: &[&str] = &["header", "footer"];
+
+/// ARIA role ที่บอกว่าไม่ใช่เนื้อหาหลัก
+const BOILERPLATE_ROLES: &[&str] = &["navigation", "banner", "contentinfo", "complementary", "search", "menu", "menubar", "dialog"];
+
+/// class/id ที่มักเป็นเมนู แถบข้าง โฆษณา ฯลฯ
+const UNLIKELY_PATTERNS: &[&str] = &[
+    "breadcrumb", "comment", "cookie", "consent", "gdpr", "footer", "menu", "navbar", "navigation", "pagination",
+    "pager", "popup", "modal", "related", "share", "sharing", "sidebar", "social", "sponsor", "advert", "banner",
+    "skip-link",
+];
+
+/// class/id ที่อาจเป็นเนื้อหา ยกเว้นจาก UNLIKELY_PATTERNS
+const MAYBE_CONTENT_PATTERNS: &[&str] = &["article", "body", "content", "main", "column", "post"];
+
+/// class/id ที่เพิ่ม/ลดคะแนนตอนเลือกกล่องเนื้อหา
+const POSITIVE_PATTERNS: &[&str] = &["article", "body", "content", "entry", "main", "page", "post", "text", "blog", "story"];
+const NEGATIVE_PATTERNS: &[&str] = &[
+    "hidden", "banner", "comment", "contact", "foot", "masthead", "meta", "promo", "related", "scroll", "share",
+    "sidebar", "sponsor", "shopping", "tags", "widget",
+];
+
+/// ย่อหน้าที่สั้นกว่านี้ไม่นำมาคิดคะแนน
+const MIN_PARAGRAPH_CHARS: usize = 25;
+
+/// ตัดเนื้อหาหลักของหน้า (แบบ readability)
+/// 1. content_selector (ตั้งต่อโดเมนใน whitelist.yaml) ถ้าเจอใช้ element นั้นทันที
+/// 2. ลบ nav/aside/header/footer, role ที่ไม่ใช่เนื้อหา, element ที่ซ่อนอยู่ และ class/id ที่เป็นเมนู/แถบข้าง
+/// 3. มี `<main>`/role="main" หรือ `<article>` เดียว -> ใช้ element นั้น
+/// 4. ไม่เช่นนั้นให้คะแนนกล่องจากความหนาแน่นของข้อความ (ย่อหน้ายาว, comma, link น้อย) แล้วเลือกกล่องคะแนนสูงสุด
+///
+/// คืน node ที่ควรแปลงตามลำดับในหน้า (ว่าง = ไม่พบเนื้อหา ให้แปลงทั้งหน้า)
+pub fn extract_main_content(document: &mut Html, content_selector: Option<&str>) -> Vec<NodeId> {
+    if let Some(selector) = content_selector {
+        match Selector::parse(selector) {
+            Ok(parsed) => {
+                if let Some(el) = document.select(&parsed).next() {
+                    return vec![el.id()];
+                }
+                println!("[main_content] ไม่พบ content_selector {:?} ในหน้า ใช้การให้คะแนนแทน", selector);
+            }
+            Err(e) => eprintln!("[main_content] content_selector {:?} ไม่ถูกต้อง: {:?}", selector, e),
+        }
+    }
+
+    remove_boilerplate(document);
+
+    let root = document.root_element();
+    let preferred = root
+        .descendent_elements()
+        .find(|el| el.value().name() == "main" || el.value().attr("role") == Some("main"))
+        .or_else(|| {
+            let mut articles = root.descendent_elements().filter(|el| el.value().name() == "article");
+            match (articles.next(), articles.next()) {
+                (Some(article), None) => Some(article),
+                _ => None,
+            }
+        });
+    if let Some(el) = preferred.filter(|el| text_len(*el) > 0) {
+        return vec![el.id()];
+    }
+
+    best_candidates(document)
+}
+
+/// ลบ element ที่ไม่ใช่เนื้อหาออกจาก DOM
+fn remove_boilerplate(document: &mut Html) {
+    let doomed: Vec<NodeId> = document
+        .root_element()
+        .descendent_elements()
+        .filter(|el| is_boilerplate(*el))
+        .map(|el| el.id())
+        .collect();
+    // element ที่อยู่ใน element ที่ลบไปแล้วถูก detach ไปพร้อมกัน ทำซ้ำได้ไม่มีผล
+    for id in doomed {
+        if let Some(mut node) = document.tree.get_mut(id) {
+            node.detach();
+        }
+    }
+}
+
+fn is_boilerplate(el: ElementRef) -> bool {
+    let element = el.value();
+    let name = element.name();
+    if matches!(name, "html" | "body" | "main" | "article") || element.attr("role") == Some("main") {
+        return false;
+    }
+    if BOILERPLATE_TAGS.contains(&name) {
+        return true;
+    }
+    if PAGE_CHROME_TAGS.contains(&name) && !has_ancestor(el, &["article", "main"]) {
+        return true;
+    }
+    if element.attr("role").is_some_and(|role| BOILERPLATE_ROLES.contains(&role)) {
+        return true;
+    }
+    if element.attr("hidden").is_some() || element.attr("aria-hidden") == Some("true") {
+        return true;
+    }
+    if element
+        .attr("style")
+        .is_some_and(|style| style.replace(' ', "").to_ascii_lowercase().contains("display:none"))
+    {
+        return true;
+    }
+
+    // ตรวจ class/id เฉพาะกล่อง ไม่รวม inline (เช่น <span class="hljs-comment"> ใน code)
+    if !matches!(name, "div" | "section" | "ul" | "ol" | "dl" | "table" | "p") {
+        return false;
+    }
+    let names = class_and_id(el);
+    // ไม่ลบ element ที่ห่อ <main>/<article> อยู่ (เช่น <div class="page-with-sidebar">)
+    matches_any(&names, UNLIKELY_PATTERNS)
+        && !matches_any(&names, MAYBE_CONTENT_PATTERNS)
+        && !el
+            .descendent_elements()
+            .any(|d| matches!(d.value().name(), "main" | "article") || d.value().attr("role") == Some("main"))
+}
+
+/// ให้คะแนนกล่องที่มีย่อหน้ายาว แล้วคืนกล่องที่ดีที่สุดพร้อมกล่องพี่น้องที่คะแนนใกล้เคียง
+fn best_candidates(document: &Html) -> Vec<NodeId> {
+    let mut scores: HashMap<NodeId, f64> = HashMap::new();
+
+    for el in document.root_element().descendent_elements() {
+        let name = el.value().name();
+        let is_paragraph = matches!(name, "p" | "pre" | "td" | "blockquote")
+            || (name == "div" && !el.child_elements().any(|c| is_block_element(c.value().name())));
+        if !is_paragraph {
+            continue;
+        }
+        let text: String = el.text().collect();
+        let len = text.split_whitespace().map(|w| w.chars().count()).sum::<usize>();
+        if len < MIN_PARAGRAPH_CHARS {
+            continue;
+        }
+        let commas = text.matches([',', '،', '，']).count() as f64;
+        let score = 1.0 + commas + (len as f64 / 100.0).min(3.0);
+
+        // parent ได้คะแนนเต็ม, ชั้นถัดไปได้ 1/2 และ 1/3
+        for (level, ancestor) in el.ancestors().filter_map(ElementRef::wrap).take(3).enumerate() {
+            if matches!(ancestor.value().name(), "html" | "body") {
+                break;
+            }
+            let entry = scores.entry(ancestor.id()).or_insert_with(|| initial_score(ancestor));
+            *entry += score / (level + 1) as f64;
+        }
+    }
+
+    // คะแนนสุดท้ายลดตามสัดส่วนข้อความที่เป็น link
+    let scored: Vec<(NodeId, f64)> = scores
+        .iter()
+        .filter_map(|(&id, &score)| {
+            let el = ElementRef::wrap(document.tree.get(id)?)?;
+            Some((id, score * (1.0 - link_density(el))))
+        })
+        .collect();
+    let Some(&(top_id, top_score)) = scored.iter().max_by(|a, b| a.1.total_cmp(&b.1)) else {
+        return Vec::new();
+    };
+    let final_scores: HashMap<NodeId, f64> = scored.into_iter().collect();
+
+    // รวมกล่องพี่น้องที่คะแนนถึงเกณฑ์ หรือเป็นย่อหน้ายาวที่ไม่ใช่ link (บทความที่แบ่งหลาย div)
+    let Some(top) = document.tree.get(top_id) else {
+        return Vec::new();
+    };
+    let Some(parent) = top.parent() else {
+        return vec![top_id];
+    };
+    let threshold = (top_score * 0.2).max(10.0);
+    parent
+        .children()
+        .filter_map(ElementRef::wrap)
+        .filter(|sibling| {
+            if sibling.id() == top_id {
+                return true;
+            }
+            if final_scores.get(&sibling.id()).is_some_and(|&s| s >= threshold) {
+                return true;
+            }
+            sibling.value().name() == "p" && text_len(*sibling) > 80 && link_density(*sibling) < 0.25
+        })
+        .map(|el| el.id())
+        .collect()
+}
+
+/// คะแนนตั้งต้นของกล่องตามชนิด tag และ class/id
+fn initial_score(el: ElementRef) -> f64 {
+    let tag_score = match el.value().name() {
+        "article" | "main" => 10.0,
+        "div" | "section" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    let names = class_and_id(el);
+    let mut class_score = 0.0;
+    if matches_any(&names, POSITIVE_PATTERNS) {
+        class_score += 25.0;
+    }
+    if matches_any(&names, NEGATIVE_PATTERNS) {
+        class_score -= 25.0;
+    }
+    tag_score + class_score
+}
+
+/// สัดส่วนตัวอักษรที่อยู่ใน <a> ต่อข้อความทั้งหมด
+fn link_density(el: ElementRef) -> f64 {
+    let total = text_len(el);
+    if total == 0 {
+        return 0.0;
+    }
+    let linked: usize = el
+        .descendent_elements()
+        .filter(|d| d.value().name() == "a")
+        .map(text_len)
+        .sum();
+    (linked as f64 / total as f64).min(1.0)
+}
+
+/// จำนวนตัวอักษรที่ไม่ใช่ whitespace
+fn text_len(el: ElementRef) -> usize {
+    el.text().map(|t| t.chars().filter(|c| !c.is_whitespace()).count()).sum()
+}
+
+fn class_and_id(el: ElementRef) -> String {
+    let element = el.value();
+    format!(
+        "{} {}",
+        element.attr("class").unwrap_or_default(),
+        element.attr("id").unwrap_or_default()
+    )
+    .to_ascii_lowercase()
+}
+
+fn matches_any(names: &str, patterns: &[&str]) -> bool {
+    patterns.iter().any(|p| names.contains(p))
+}
+
+fn has_ancestor(el: ElementRef, tags: &[&str]) -> bool {
+    el.ancestors()
+        .filter_map(ElementRef::wrap)
+        .any(|a| tags.contains(&a.value().name()))
+}
+
+fn is_block_element(name: &str) -> bool {
+    matches!(
+        name,
+        "div" | "p" | "pre" | "table" | "ul" | "ol" | "dl" | "blockquote" | "section" | "article" | "header" | "footer"
+            | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "form" | "figure"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crawler::html_to_markdown::{convert_html, ConvertOptions};
+
+    const PARAGRAPH: &str = "This paragraph is long enough to count, with commas, clauses, and plenty of words in it.";
+
+    /// ข้อความของ node ที่ extract_main_content เลือก (คั่นด้วย |)
+    fn extracted(html: &str, content_selector: Option<&str>) -> String {
+        let mut document = Html::parse_document(html);
+        let ids = extract_main_content(&mut document, content_selector);
+        ids.iter()
+            .filter_map(|id| document.tree.get(*id).and_then(ElementRef::wrap))
+            .map(|el| el.text().collect::<Vec<_>>().join(" ").split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+
+    #[test]
+    fn drops_nav_sidebar_and_footer() {
+        let html = format!(
+            "<html><body><header><a href=\"/\">Home</a></header><nav><a href=\"/docs\">Docs</a></nav>\
+<div class=\"sidebar\"><p>Sidebar: {p}</p></div>\
+<div id=\"page\"><p>First. {p}</p><p>Second. {p}</p></div>\
+<div hidden><p>Hidden. {p}</p></div><div style=\"display: none\"><p>Invisible. {p}</p></div>\
+<footer><p>Copyright, {p}</p></footer></body></html>",
+            p = PARAGRAPH
+        );
+        let text = extracted(&html, None);
+        assert!(text.contains("First.") && text.contains("Second."), "{}", text);
+        for dropped in ["Home", "Docs", "Sidebar", "Hidden", "Invisible", "Copyright"] {
+            assert!(!text.contains(dropped), "{} in {}", dropped, text);
+        }
+    }
+
+    #[test]
+    fn single_article_is_used_with_its_own_header_and_footer() {
+        let html = format!(
+            "<html><body><header>Site</header><div class=\"menu\"><ul><li>A</li></ul></div>\
+<article><header><h1>Title</h1></header><p>{p}</p><footer>By author</footer></article>\
+<footer>Site footer</footer></body></html>",
+            p = PARAGRAPH
+        );
+        assert_eq!(extracted(&html, None), format!("Title {} By author", PARAGRAPH));
+    }
+
+    #[test]
+    fn sidebar_content_class_is_kept() {
+        let html = format!(
+            "<html><body><div class=\"layout\"><aside>Links</aside>\
+<div class=\"sidebar-content\"><p>Real. {p}</p><p>More. {p}</p></div></div></body></html>",
+            p = PARAGRAPH
+        );
+        let text = extracted(&html, None);
+        assert!(text.contains("Real.") && text.contains("More."), "{}", text);
+        assert!(!text.contains("Links"), "{}", text);
+    }
+
+    #[test]
+    fn content_selector_wins_and_falls_back_to_scoring() {
+        let html = format!(
+            "<html><body><nav id=\"docs\"><p>Selected</p></nav><main><p>{p}</p></main></body></html>",
+            p = PARAGRAPH
+        );
+        assert_eq!(extracted(&html, Some("#docs")), "Selected");
+        assert_eq!(extracted(&html, Some("#missing")), PARAGRAPH);
+        assert_eq!(extracted(&html, Some("[[invalid")), PARAGRAPH);
+    }
+
+    #[test]
+    fn no_candidate_converts_whole_body() {
+        let html = "<html><body><nav><a href=\"/\">Home</a></nav><span>Short note</span></body></html>";
+        assert_eq!(extracted(html, None), "");
+        let options = ConvertOptions { extract_main_content: true, ..ConvertOptions::default() };
+        assert_eq!(convert_html("https://example.com/", html, &options), "[Home](https://example.com/)\n\nShort note");
+    }
+}
@@ -1,45 +1,87 @@
 use spider::url::Url;
 use std::error::Error;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-pub fn write_markdown_file(url: &str, markdown: &str) -> Result<PathBuf, Box<dyn Error>> {
-    let slug = slug_from_url(url);
+use super::front_matter::FrontMatter;
+use super::html_fetcher::FetchedPage;
+
+/// ไฟล์ตั้งต้นของหน้าเว็บ output/<slug>.md (ชื่อที่ใช้จริงจอง/กันชนผ่าน LinkMap::reserve)
+/// slug มาจาก URL สุดท้ายหลัง redirect, n > 1 ต่อท้ายเป็น <slug>-<n>.md
+pub fn markdown_path(page: &FetchedPage, n: usize) -> PathBuf {
+    let slug = slug_from_url(&page.final_url);
     let mut path = PathBuf::from("output");
-    fs::create_dir_all(&path)?;
-    path.push(slug);
+    if n > 1 {
+        path.push(format!("{}-{}", slug, n));
+    } else {
+        path.push(slug);
+    }
     path.set_extension("md");
-    fs::write(&path, markdown)?;
-    Ok(path)
+    path
+}
+
+/// เขียน markdown ลง path
+/// ถ้าส่ง front_matter มา จะเขียน YAML front matter ไว้หัวไฟล์
+pub fn write_markdown_file(path: &Path, markdown: &str, front_matter: Option<&FrontMatter>) -> Result<(), Box<dyn Error>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    match front_matter {
+        Some(front_matter) => fs::write(path, front_matter.prepend(markdown)?)?,
+        None => fs::write(path, markdown)?,
+    }
+    Ok(())
 }
 
 fn slug_from_url(url: &str) -> String {
     if let Ok(parsed) = Url::parse(url) {
         if let Some(mut segments) = parsed
             .path_segments()
-            .map(|segments| segments.filter(|s| !s.is_empty()).map(sanitize_segment).collect::<Vec<_>>())
+            .map(|segments| segments.filter(|s| !s.is_empty()).map(segment_slug).collect::<Vec<_>>())
+            && let Some(seg) = segments.pop()
+            && !seg.is_empty()
         {
-            if let Some(seg) = segments.pop() {
-                if !seg.is_empty() {
-                    return seg;
-                }
-            }
+            return seg;
         }
         return "index".into();
     }
     sanitize_segment(url)
 }
 
+/// ไฟล์ .pdf เก็บนามสกุลเป็น -pdf (code-of-conduct.pdf -> code-of-conduct-pdf) ไม่ให้ชนกับหน้า HTML ชื่อเดียวกัน
+/// segment อื่นใช้ sanitize_segment ตามเดิม (foo.html -> foohtml)
+fn segment_slug(segment: &str) -> String {
+    match segment.to_ascii_lowercase().strip_suffix(".pdf") {
+        Some(stem) => format!("{}-pdf", sanitize_segment(stem)).trim_start_matches('-').to_string(),
+        None => sanitize_segment(segment),
+    }
+}
+
 fn sanitize_segment(segment: &str) -> String {
     let mut slug = String::new();
     for ch in segment.chars() {
         if ch.is_ascii_alphanumeric() {
             slug.push(ch.to_ascii_lowercase());
-        } else if ch.is_ascii_whitespace() || ch == '-' || ch == '_' {
-            if !slug.ends_with('-') {
-                slug.push('-');
-            }
+        } else if (ch.is_ascii_whitespace() || ch == '-' || ch == '_') && !slug.ends_with('-') {
+            slug.push('-');
         }
     }
     slug.trim_matches('-').to_string()
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slug_keeps_pdf_extension_readable() {
+        assert_eq!(slug_from_url("https://e.com/policies/code-of-conduct.pdf"), "code-of-conduct-pdf");
+        assert_eq!(slug_from_url("https://e.com/files/REPORT_2024.PDF"), "report-2024-pdf");
+        assert_eq!(slug_from_url("https://e.com/files/.pdf"), "pdf");
+        // dot อื่นถูกตัดทิ้งเหมือนเดิม
+        assert_eq!(slug_from_url("https://e.com/docs/foo.html"), "foohtml");
+        assert_eq!(slug_from_url("https://e.com/release/v1.2"), "v12");
+        assert_eq!(slug_from_url("https://e.com/docs/Getting_Started/"), "getting-started");
+        assert_eq!(slug_from_url("https://e.com/"), "index");
+    }
+}
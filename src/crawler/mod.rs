@@ -1,7 +1,22 @@
+#[allow(clippy::module_inception)]
 pub mod crawler;
+pub mod canonical;
+pub mod charset;
 pub mod robots;
+pub mod robots_txt;
+pub mod sitemap;
+pub mod sitemap_selection;
 pub mod html_fetcher;
 pub mod domain_detector;
+pub mod front_matter;
 pub mod chrome_fetcher;
 pub mod markdown_writer;
-pub mod html_to_markdown;
\ No newline at end of file
+pub mod page_filter;
+#[cfg(feature = "pdf")]
+pub mod pdf_handler;
+pub mod rate_limiter;
+pub mod redirect;
+pub mod retry;
+pub mod html_to_markdown;
+pub mod link_rewriter;
+pub mod main_content;
\ No newline at end of file
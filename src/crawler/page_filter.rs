@@ -0,0 +1,221 @@
+use ego_tree::NodeRef;
+use scraper::{Html, Node};
+use std::fmt;
+
+use super::html_fetcher::FetchedPage;
+use super::redirect::RedirectRules;
+use super::retry::status_reason;
+use crate::config::config::AppConfig;
+
+/// ข้อความใน <title>/<h1> ที่บ่งว่าเป็นหน้า "ไม่พบ" แม้ server ตอบ 200 (soft-404)
+const DEFAULT_SOFT_404_PATTERNS: &[&str] = &[
+    "page not found",
+    "404 not found",
+    "error 404",
+    "ไม่พบหน้า",
+];
+
+/// เหตุผลที่ข้ามหน้าเว็บแทนการแปลงเป็น markdown
+#[derive(Debug, Clone)]
+pub enum SkipReason {
+    /// status ไม่ใช่ 2xx
+    HttpStatus(u16),
+    /// ตอบ 2xx แต่ title/h1 ตรงกับ soft_404_patterns
+    Soft404(String),
+    /// ไม่มีข้อความที่มองเห็นได้ (หรือน้อยกว่า min_text_chars)
+    EmptyBody(usize),
+    /// redirect ไป host ที่ cross_host_redirects ไม่อนุญาต
+    CrossHostRedirect(String),
+    /// redirect ไป URL ที่ robots.txt ไม่อนุญาต
+    RobotsDisallowed(String),
+    /// ปลายทางเดียวกับหน้าที่บันทึกไปแล้วในการ crawl นี้
+    Duplicate(String),
+    /// content type อยู่ใน skip_content_types หรือไม่มี handler รองรับ
+    ContentType(String),
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::HttpStatus(status) => f.write_str(&status_reason(*status)),
+            SkipReason::Soft404(pattern) => write!(f, "soft-404 (พบ \"{}\")", pattern),
+            SkipReason::EmptyBody(chars) => write!(f, "เนื้อหาว่าง ({} ตัวอักษร)", chars),
+            SkipReason::CrossHostRedirect(url) => write!(f, "redirect ไป host อื่น ({})", url),
+            SkipReason::RobotsDisallowed(url) => write!(f, "redirect ไป URL ที่ robots.txt ห้าม ({})", url),
+            SkipReason::Duplicate(url) => write!(f, "ซ้ำกับ {}", url),
+            SkipReason::ContentType(content_type) => write!(f, "ไม่แปลง content type {}", content_type),
+        }
+    }
+}
+
+/// กฎการข้ามหน้าเว็บหลังโหลด (ตั้งค่าได้ใน app.yaml)
+/// - skip_non_success: ข้าม status ที่ไม่ใช่ 2xx
+/// - soft_404_patterns: ข้อความใน <title>/<h1> ที่ถือว่าเป็น soft-404 (ไม่สนตัวพิมพ์)
+/// - min_text_chars: จำนวนตัวอักษรที่มองเห็นได้ขั้นต่ำ
+/// - redirects: ข้ามหน้าที่ redirect ไป host ที่ไม่อนุญาต
+#[derive(Debug, Clone)]
+pub struct PageFilter {
+    pub skip_non_success: bool,
+    pub soft_404_patterns: Vec<String>,
+    pub min_text_chars: usize,
+    pub redirects: RedirectRules,
+}
+
+impl PageFilter {
+    pub fn from_config(cfg: &AppConfig) -> Self {
+        let patterns = cfg
+            .soft_404_patterns
+            .clone()
+            .unwrap_or_else(|| DEFAULT_SOFT_404_PATTERNS.iter().map(|p| p.to_string()).collect());
+        Self {
+            skip_non_success: cfg.skip_non_success.unwrap_or(true),
+            soft_404_patterns: patterns
+                .into_iter()
+                .map(|p| p.trim().to_lowercase())
+                .filter(|p| !p.is_empty())
+                .collect(),
+            min_text_chars: cfg.min_text_chars.unwrap_or(1),
+            redirects: RedirectRules::from_config(cfg),
+        }
+    }
+
+    /// คืน Some(reason) ถ้าควรข้ามหน้านี้
+    pub fn check(&self, page: &FetchedPage) -> Option<SkipReason> {
+        if self.skip_non_success && !page.is_success() {
+            return Some(SkipReason::HttpStatus(page.status));
+        }
+        if !self.redirects.allows(&page.url, &page.final_url) {
+            return Some(SkipReason::CrossHostRedirect(page.final_url.clone()));
+        }
+        // soft-404 และเนื้อหาว่างตรวจได้เฉพาะหน้า HTML
+        if !page.is_html() {
+            return None;
+        }
+
+        let document = Html::parse_document(&page.html());
+        let headings = [element_text(&document, "title"), element_text(&document, "h1")];
+        for pattern in &self.soft_404_patterns {
+            if headings.iter().flatten().any(|text| text.contains(pattern.as_str())) {
+                return Some(SkipReason::Soft404(pattern.clone()));
+            }
+        }
+
+        let chars = visible_text_len(&document);
+        if chars < self.min_text_chars {
+            return Some(SkipReason::EmptyBody(chars));
+        }
+        None
+    }
+}
+
+/// element ที่ข้อความข้างในไม่แสดงบนหน้า
+const HIDDEN_TAGS: &[&str] = &["script", "style", "noscript", "template"];
+
+/// ข้อความ (ตัวพิมพ์เล็ก, ยุบ whitespace รวม &nbsp;) ใน element แรกของ tag
+fn element_text(document: &Html, tag: &str) -> Option<String> {
+    let el = document.root_element().descendent_elements().find(|el| el.value().name() == tag)?;
+    let text = el.text().collect::<String>().to_lowercase();
+    Some(text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// จำนวนตัวอักษรที่มองเห็นได้ใน body (ไม่นับ script/style/noscript/template และ whitespace)
+fn visible_text_len(document: &Html) -> usize {
+    document
+        .root_element()
+        .children()
+        .filter(|node| node.value().as_element().is_some_and(|el| el.name() == "body"))
+        .map(text_len)
+        .sum()
+}
+
+fn text_len(node: NodeRef<Node>) -> usize {
+    match node.value() {
+        Node::Text(text) => text.chars().filter(|c| !c.is_whitespace()).count(),
+        Node::Element(el) if HIDDEN_TAGS.contains(&el.name()) => 0,
+        _ => node.children().map(text_len).sum(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crawler::html_fetcher::FetchMode;
+    use std::time::Duration;
+
+    fn page(status: u16, final_url: &str, content_type: &str, body: &str) -> FetchedPage {
+        let url = "https://example.com/page";
+        let mut page = FetchedPage::from_spider_page(url, &spider::page::Page::default(), FetchMode::HttpRequest, Duration::ZERO);
+        page.final_url = final_url.to_string();
+        page.status = status;
+        page.content_type = content_type.to_string();
+        page.charset = "utf-8".to_string();
+        page.bytes = body.as_bytes().to_vec();
+        page
+    }
+
+    fn html(status: u16, body: &str) -> FetchedPage {
+        page(status, "https://example.com/page", "text/html", body)
+    }
+
+    fn filter() -> PageFilter {
+        PageFilter::from_config(&AppConfig::default())
+    }
+
+    #[test]
+    fn skips_non_success_status_unless_disabled() {
+        let body = "<html><body><p>content</p></body></html>";
+        for status in [301, 404, 500, 524] {
+            assert!(matches!(filter().check(&html(status, body)), Some(SkipReason::HttpStatus(s)) if s == status));
+        }
+        assert!(filter().check(&html(204, body)).is_none());
+
+        let keep_errors = PageFilter { skip_non_success: false, ..filter() };
+        assert!(keep_errors.check(&html(404, body)).is_none());
+    }
+
+    #[test]
+    fn skips_redirect_to_other_site() {
+        let body = "<p>content</p>";
+        let cross = page(200, "https://other.org/page", "text/html", body);
+        assert!(matches!(filter().check(&cross), Some(SkipReason::CrossHostRedirect(url)) if url == "https://other.org/page"));
+        let same_site = page(200, "https://www.example.com/page", "text/html", body);
+        assert!(filter().check(&same_site).is_none());
+    }
+
+    #[test]
+    fn skips_soft_404_by_title_or_h1() {
+        let cases = [
+            "<html><head><title>Oops! Page\n  Not Found</title></head><body><p>Try search.</p></body></html>",
+            "<html><body><H1 class='err'>Error&nbsp;404</H1><p>Gone.</p></body></html>",
+            "<html><head><title>ไม่พบหน้าที่ต้องการ</title></head><body><p>x</p></body></html>",
+        ];
+        for body in cases {
+            assert!(matches!(filter().check(&html(200, body)), Some(SkipReason::Soft404(_))), "{}", body);
+        }
+        // pattern ในเนื้อหาหรือ h2 ไม่ถือเป็น soft-404
+        let article = "<html><head><title>Fixing 404 errors</title></head>\
+            <body><h1>Guide</h1><h2>Page not found</h2><p>Why a page not found error happens.</p></body></html>";
+        assert!(filter().check(&html(200, article)).is_none());
+    }
+
+    #[test]
+    fn counts_only_visible_body_text() {
+        let body = "<html><head><title>Title text</title><style>p { color: red }</style></head>\
+            <body><script>var lots = 'of script text';</script><noscript>enable js</noscript>\
+            <template><p>hidden</p></template><p> ab  c </p><div><span>d</span></div></body></html>";
+        let strict = PageFilter { min_text_chars: 5, ..filter() };
+        assert!(matches!(strict.check(&html(200, body)), Some(SkipReason::EmptyBody(4))));
+        let lenient = PageFilter { min_text_chars: 4, ..filter() };
+        assert!(lenient.check(&html(200, body)).is_none());
+
+        // default min_text_chars = 1: หน้าที่มีแต่ script ว่าง
+        let empty = "<html><body><div id='app'></div><script src='/app.js'></script></body></html>";
+        assert!(matches!(filter().check(&html(200, empty)), Some(SkipReason::EmptyBody(0))));
+    }
+
+    #[test]
+    fn non_html_skips_only_status_and_redirect_checks() {
+        let pdf = page(200, "https://example.com/page", "application/pdf", "");
+        assert!(filter().check(&pdf).is_none());
+    }
+}
@@ -0,0 +1,276 @@
+use pdf_extract::{output_doc, Document, MediaBox, OutputDev, OutputError, Transform};
+use std::collections::HashMap;
+use std::error::Error;
+use std::panic::{self, AssertUnwindSafe};
+
+use super::html_fetcher::{ContentHandler, FetchedPage};
+
+/// แปลง PDF เป็น markdown (เปิดด้วย cargo feature "pdf")
+/// - แต่ละหน้าคั่นด้วย `---`
+/// - บรรทัดที่ font ใหญ่กว่าเนื้อหาปกติกลายเป็น heading
+pub struct PdfHandler;
+
+impl ContentHandler for PdfHandler {
+    fn name(&self) -> &str {
+        "pdf"
+    }
+
+    fn handles(&self, content_type: &str) -> bool {
+        matches!(content_type, "application/pdf" | "application/x-pdf")
+    }
+
+    fn to_markdown(&self, page: &FetchedPage) -> Result<String, Box<dyn Error>> {
+        // pdf-extract panic ได้กับไฟล์ที่เสีย จึงแปลง panic เป็น error แทนให้ crawl ต่อได้
+        let pages = panic::catch_unwind(AssertUnwindSafe(|| extract_lines(&page.bytes)))
+            .map_err(|_| format!("pdf-extract panic ขณะอ่าน {}", page.final_url))??;
+        Ok(render_markdown(&pages))
+    }
+}
+
+/// บรรทัดข้อความหนึ่งบรรทัดใน PDF
+struct Line {
+    text: String,
+    font_size: f64,
+    /// ระยะห่างแนวตั้งจากบรรทัดก่อนหน้า (0 สำหรับบรรทัดแรกของหน้า)
+    gap: f64,
+}
+
+/// อ่าน PDF แล้วคืนบรรทัดของแต่ละหน้า
+fn extract_lines(bytes: &[u8]) -> Result<Vec<Vec<Line>>, OutputError> {
+    let mut doc = Document::load_mem(bytes)?;
+    if doc.is_encrypted() {
+        // PDF ที่เข้ารหัสด้วย password ว่าง (จำกัดแค่สิทธิ์ print/copy) ยังอ่านได้
+        doc.decrypt("").map_err(OutputError::PdfError)?;
+    }
+    let mut output = LineCollector::default();
+    output_doc(&doc, &mut output)?;
+    Ok(output.pages)
+}
+
+/// OutputDev ที่รวมตัวอักษรเป็นบรรทัด พร้อมขนาด font ของแต่ละบรรทัด
+/// (ใช้เกณฑ์ตำแหน่งแบบเดียวกับ PlainTextOutput ของ pdf-extract)
+#[derive(Default)]
+struct LineCollector {
+    pages: Vec<Vec<Line>>,
+    lines: Vec<Line>,
+    current: String,
+    current_size: f64,
+    page_height: f64,
+    /// y ของบรรทัดที่กำลังสะสม และของบรรทัดก่อนหน้า
+    line_y: f64,
+    prev_line_y: Option<f64>,
+    last_y: f64,
+    last_end: f64,
+    first_char: bool,
+}
+
+impl LineCollector {
+    fn flush_line(&mut self) {
+        let text = self.current.trim().to_string();
+        if !text.is_empty() {
+            let gap = self.prev_line_y.map(|prev| (self.line_y - prev).abs()).unwrap_or(0.0);
+            self.lines.push(Line {
+                text,
+                font_size: self.current_size,
+                gap,
+            });
+            self.prev_line_y = Some(self.line_y);
+        }
+        self.current.clear();
+        self.current_size = 0.0;
+    }
+}
+
+impl OutputDev for LineCollector {
+    fn begin_page(&mut self, _page_num: u32, media_box: &MediaBox, _art_box: Option<(f64, f64, f64, f64)>) -> Result<(), OutputError> {
+        self.page_height = media_box.ury - media_box.lly;
+        self.lines.clear();
+        self.prev_line_y = None;
+        self.last_end = f64::MAX;
+        self.first_char = false;
+        Ok(())
+    }
+
+    fn end_page(&mut self) -> Result<(), OutputError> {
+        self.flush_line();
+        self.pages.push(std::mem::take(&mut self.lines));
+        Ok(())
+    }
+
+    fn output_character(&mut self, trm: &Transform, width: f64, _spacing: f64, font_size: f64, char: &str) -> Result<(), OutputError> {
+        // ตำแหน่งหลังกลับแกน y ให้นับจากบนลงล่าง
+        let x = trm.m31;
+        let y = self.page_height - trm.m32;
+        let size_x = font_size * (trm.m11 + trm.m21);
+        let size_y = font_size * (trm.m12 + trm.m22);
+        let size = (size_x * size_y).abs().sqrt();
+
+        if self.first_char {
+            let new_line = (y - self.last_y).abs() > size * 1.5
+                || (x < self.last_end && (y - self.last_y).abs() > size * 0.5);
+            if new_line {
+                self.flush_line();
+            } else if x > self.last_end + size * 0.1 {
+                self.current.push(' ');
+            }
+        }
+        if self.current.is_empty() {
+            self.line_y = y;
+        }
+        self.current.push_str(char);
+        self.current_size = self.current_size.max(size);
+        self.first_char = false;
+        self.last_y = y;
+        self.last_end = x + width * size;
+        Ok(())
+    }
+
+    fn begin_word(&mut self) -> Result<(), OutputError> {
+        self.first_char = true;
+        Ok(())
+    }
+
+    fn end_word(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn end_line(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+}
+
+/// ขนาด font ของเนื้อหาปกติ = ขนาดที่มีจำนวนตัวอักษรมากที่สุด (ปัดทีละ 0.5pt)
+fn body_font_size(pages: &[Vec<Line>]) -> f64 {
+    let mut counts: HashMap<i64, usize> = HashMap::new();
+    for line in pages.iter().flatten() {
+        *counts.entry((line.font_size * 2.0).round() as i64).or_default() += line.text.chars().count();
+    }
+    counts
+        .into_iter()
+        .max_by_key(|&(size, count)| (count, -size))
+        .map(|(size, _)| size as f64 / 2.0)
+        .unwrap_or(0.0)
+}
+
+/// ระดับ heading จากอัตราส่วน font ต่อเนื้อหาปกติ (บรรทัดยาวไม่ถือเป็น heading)
+fn heading_level(line: &Line, body_size: f64) -> Option<usize> {
+    if body_size <= 0.0 || line.text.chars().count() > 120 {
+        return None;
+    }
+    let ratio = line.font_size / body_size;
+    if ratio >= 1.6 {
+        Some(1)
+    } else if ratio >= 1.3 {
+        Some(2)
+    } else if ratio >= 1.15 {
+        Some(3)
+    } else {
+        None
+    }
+}
+
+fn render_markdown(pages: &[Vec<Line>]) -> String {
+    let body_size = body_font_size(pages);
+    let mut out = String::new();
+
+    for (idx, lines) in pages.iter().enumerate() {
+        if idx > 0 && !out.is_empty() {
+            out.push_str("\n\n---\n\n");
+        }
+        let mut paragraph = String::new();
+        for line in lines {
+            let level = heading_level(line, body_size);
+            // บรรทัดห่างกันมากกว่าปกติ = ขึ้นย่อหน้าใหม่
+            let paragraph_break = line.gap > line.font_size.max(body_size) * 1.8;
+            if (level.is_some() || paragraph_break) && !paragraph.is_empty() {
+                push_block(&mut out, &paragraph);
+                paragraph.clear();
+            }
+            match level {
+                Some(level) => push_block(&mut out, &format!("{} {}", "#".repeat(level), line.text)),
+                None => {
+                    if !paragraph.is_empty() {
+                        paragraph.push(' ');
+                    }
+                    paragraph.push_str(&line.text);
+                }
+            }
+        }
+        if !paragraph.is_empty() {
+            push_block(&mut out, &paragraph);
+        }
+    }
+
+    if out.trim().is_empty() {
+        "No text extracted from PDF.\n".to_string()
+    } else {
+        out.push('\n');
+        out
+    }
+}
+
+fn push_block(out: &mut String, block: &str) {
+    if !out.is_empty() && !out.ends_with("\n\n") {
+        out.push_str("\n\n");
+    }
+    out.push_str(block.trim());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str, font_size: f64, gap: f64) -> Line {
+        Line {
+            text: text.to_string(),
+            font_size,
+            gap,
+        }
+    }
+
+    #[test]
+    fn body_font_size_is_the_size_with_most_characters() {
+        let pages = vec![
+            vec![line("Title", 24.0, 0.0), line("short", 12.2, 30.0)],
+            vec![line("a much longer body line", 11.9, 0.0), line("Note", 9.0, 14.0)],
+        ];
+        // 12.2 และ 11.9 ปัดเป็น 12.0 เหมือนกัน
+        assert_eq!(body_font_size(&pages), 12.0);
+        // จำนวนตัวอักษรเท่ากันเลือกขนาดที่เล็กกว่า
+        assert_eq!(body_font_size(&[vec![line("abcd", 14.0, 0.0), line("efgh", 10.0, 0.0)]]), 10.0);
+        assert_eq!(body_font_size(&[]), 0.0);
+    }
+
+    #[test]
+    fn heading_level_thresholds() {
+        let cases = [(19.5, Some(1)), (16.0, Some(2)), (14.0, Some(3)), (13.7, None), (12.0, None)];
+        for (size, expected) in cases {
+            assert_eq!(heading_level(&line("Heading", size, 0.0), 12.0), expected, "{}", size);
+        }
+        assert_eq!(heading_level(&line(&"x".repeat(121), 24.0, 0.0), 12.0), None);
+        assert_eq!(heading_level(&line("Heading", 24.0, 0.0), 0.0), None);
+    }
+
+    #[test]
+    fn render_markdown_builds_headings_paragraphs_and_page_breaks() {
+        let pages = vec![
+            vec![
+                line("Annual Report", 24.0, 0.0),
+                line("The first paragraph", 12.0, 40.0),
+                line("continues here.", 12.0, 14.0),
+                line("A second paragraph.", 12.0, 30.0),
+            ],
+            vec![line("Results", 16.0, 0.0), line("Numbers went up.", 12.0, 20.0)],
+        ];
+        assert_eq!(
+            render_markdown(&pages),
+            "# Annual Report\n\nThe first paragraph continues here.\n\nA second paragraph.\n\n---\n\n## Results\n\nNumbers went up.\n"
+        );
+    }
+
+    #[test]
+    fn render_markdown_without_text() {
+        assert_eq!(render_markdown(&[]), "No text extracted from PDF.\n");
+        assert_eq!(render_markdown(&[vec![], vec![line("Only page two", 12.0, 0.0)]]), "Only page two\n");
+    }
+}
@@ -0,0 +1,286 @@
+use spider::url::Url;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::time::{sleep, Duration, Instant};
+
+use crate::config::config::AppConfig;
+
+/// อัตราการยิง request ของ host หนึ่ง
+/// - requests_per_second <= 0 หมายถึงไม่จำกัด
+/// - burst คือจำนวน request ที่ยิงติดกันได้ก่อนต้องรอ
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub requests_per_second: f64,
+    pub burst: u32,
+}
+
+impl RateLimit {
+    /// อ่านค่า global จาก AppConfig
+    /// ถ้าไม่ได้ตั้ง requests_per_second จะแปลงจาก delay_ms (1 request ต่อ delay_ms)
+    pub fn from_config(cfg: &AppConfig) -> Self {
+        let requests_per_second = cfg.requests_per_second.unwrap_or_else(|| {
+            match cfg.delay_ms.unwrap_or(250) {
+                0 => 0.0,
+                ms => 1000.0 / ms as f64,
+            }
+        });
+        Self {
+            requests_per_second,
+            burst: cfg.rate_limit_burst.unwrap_or(1).max(1),
+        }
+    }
+
+    fn is_unlimited(&self) -> bool {
+        !(self.requests_per_second > 0.0 && self.requests_per_second.is_finite())
+    }
+}
+
+/// ค่า rate limit เฉพาะโดเมนจาก whitelist.yaml
+#[derive(Debug, Clone)]
+pub struct DomainRateLimit {
+    pub domain: String,
+    pub include_subdomains: bool,
+    pub limit: RateLimit,
+}
+
+impl DomainRateLimit {
+    fn matches(&self, host: &str) -> bool {
+        host == self.domain || (self.include_subdomains && host.ends_with(&format!(".{}", self.domain)))
+    }
+}
+
+/// token bucket ของ host หนึ่ง
+/// tokens ติดลบได้ = มี request จองคิวรออยู่
+struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit, now: Instant) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst as f64,
+            updated: now,
+        }
+    }
+
+    /// จอง 1 token คืนเวลาที่ต้องรอก่อนเริ่ม request
+    fn reserve(&mut self, now: Instant) -> Duration {
+        if self.limit.is_unlimited() {
+            return Duration::ZERO;
+        }
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.limit.requests_per_second).min(self.limit.burst as f64);
+        self.updated = now;
+
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.limit.requests_per_second)
+        }
+    }
+}
+
+/// rate limiter ต่อ host ที่ใช้ร่วมกันทุก fetcher (หน้าเว็บ, sitemap, robots.txt)
+/// - ค่า default มาจาก app.yaml, ค่าเฉพาะโดเมนมาจาก whitelist.yaml
+/// - Crawl-delay/Request-rate จาก robots.txt ทำให้ host นั้นเข้มขึ้นได้ แต่ไม่หลวมลง
+pub struct RateLimiter {
+    default_limit: RateLimit,
+    domain_limits: Vec<DomainRateLimit>,
+    crawl_delays: Mutex<HashMap<String, Duration>>,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(default_limit: RateLimit, domain_limits: Vec<DomainRateLimit>) -> Self {
+        Self {
+            default_limit,
+            domain_limits,
+            crawl_delays: Mutex::new(HashMap::new()),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// รอจนกว่า host ของ url จะยิง request ได้
+    pub async fn acquire(&self, url: &str) {
+        let host = host_of(url);
+        let now = Instant::now();
+        let wait = {
+            let limit = self.limit_for(&host);
+            let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+            buckets
+                .entry(host.clone())
+                .or_insert_with(|| TokenBucket::new(limit, now))
+                .reserve(now)
+        };
+
+        if !wait.is_zero() {
+            println!("[rate_limiter] {} รอ {:?}", host, wait);
+            sleep(wait).await;
+        }
+    }
+
+    /// ใช้ Crawl-delay/Request-rate จาก robots.txt กับ host ของ url
+    /// อัตราที่ใช้จริงคือค่าที่เข้มกว่าระหว่าง config กับที่ site ขอ
+    pub fn apply_crawl_delay(&self, url: &str, delay: Duration) {
+        let host = host_of(url);
+        self.crawl_delays
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(host.clone(), delay);
+
+        let limit = self.limit_for(&host);
+        if let Some(bucket) = self
+            .buckets
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get_mut(&host)
+        {
+            bucket.limit = limit;
+            bucket.tokens = bucket.tokens.min(limit.burst as f64);
+        }
+        println!(
+            "[rate_limiter] {} -> {:.3} req/s, burst {} (robots.txt ขอ delay {:?})",
+            host, limit.requests_per_second, limit.burst, delay
+        );
+    }
+
+    /// ระยะห่างขั้นต่ำระหว่าง request ของ host ของ url (ZERO = ไม่จำกัด)
+    /// สำหรับ crawler ที่เรียก acquire ต่อ request ไม่ได้ เช่น native spider crawl ซึ่งยิงทีละ request ตาม delay
+    pub fn min_interval(&self, url: &str) -> Duration {
+        let limit = self.limit_for(&host_of(url));
+        if limit.is_unlimited() {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(1.0 / limit.requests_per_second)
+        }
+    }
+
+    fn limit_for(&self, host: &str) -> RateLimit {
+        let normalized = host.strip_prefix("www.").unwrap_or(host);
+        let mut limit = self
+            .domain_limits
+            .iter()
+            .find(|d| d.matches(normalized))
+            .map(|d| d.limit)
+            .unwrap_or(self.default_limit);
+
+        let crawl_delay = self
+            .crawl_delays
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(host)
+            .copied();
+        if let Some(delay) = crawl_delay.filter(|d| !d.is_zero()) {
+            let site_rate = 1.0 / delay.as_secs_f64();
+            if limit.is_unlimited() || site_rate < limit.requests_per_second {
+                limit.requests_per_second = site_rate;
+            }
+            // site ขอเว้นระยะทุก request จึงไม่ให้ยิงเป็น burst
+            limit.burst = 1;
+        }
+        limit
+    }
+}
+
+fn host_of(url: &str) -> String {
+    Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_ascii_lowercase))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit(requests_per_second: f64, burst: u32) -> RateLimit {
+        RateLimit { requests_per_second, burst }
+    }
+
+    fn domain(domain: &str, include_subdomains: bool, rate: f64) -> DomainRateLimit {
+        DomainRateLimit {
+            domain: domain.to_string(),
+            include_subdomains,
+            limit: limit(rate, 1),
+        }
+    }
+
+    #[test]
+    fn reserve_allows_burst_then_spaces_requests() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(limit(2.0, 3), start);
+        let waits: Vec<_> = (0..5).map(|_| bucket.reserve(start)).collect();
+        let ms = |m| Duration::from_millis(m);
+        assert_eq!(waits, [Duration::ZERO, Duration::ZERO, Duration::ZERO, ms(500), ms(1000)]);
+
+        // 10 วินาทีต่อมา token เต็มแค่ burst ไม่สะสมเกิน
+        let later = start + Duration::from_secs(10);
+        let waits: Vec<_> = (0..4).map(|_| bucket.reserve(later)).collect();
+        assert_eq!(waits, [Duration::ZERO, Duration::ZERO, Duration::ZERO, ms(500)]);
+    }
+
+    #[test]
+    fn unlimited_bucket_never_waits() {
+        let now = Instant::now();
+        for rate in [0.0, -1.0, f64::INFINITY, f64::NAN] {
+            let mut bucket = TokenBucket::new(limit(rate, 1), now);
+            assert!((0..10).all(|_| bucket.reserve(now).is_zero()), "{}", rate);
+        }
+    }
+
+    #[test]
+    fn limit_for_matches_domain_without_www() {
+        let limiter = RateLimiter::new(
+            limit(4.0, 2),
+            vec![domain("example.com", false, 1.0), domain("docs.rs", true, 0.5)],
+        );
+        let rate = |host: &str| limiter.limit_for(host).requests_per_second;
+        assert_eq!(rate("example.com"), 1.0);
+        assert_eq!(rate("www.example.com"), 1.0);
+        assert_eq!(rate("api.example.com"), 4.0);
+        assert_eq!(rate("www.docs.rs"), 0.5);
+        assert_eq!(rate("static.docs.rs"), 0.5);
+        assert_eq!(rate("notdocs.rs"), 4.0);
+    }
+
+    #[test]
+    fn apply_crawl_delay_keeps_the_stricter_rate() {
+        let limiter = RateLimiter::new(limit(4.0, 5), vec![domain("slow.example", false, 0.1)]);
+        // site ขอช้ากว่า config -> ใช้ของ site และไม่ให้ burst
+        limiter.apply_crawl_delay("https://fast.example/robots.txt", Duration::from_secs(2));
+        let fast = limiter.limit_for("fast.example");
+        assert_eq!((fast.requests_per_second, fast.burst), (0.5, 1));
+        assert_eq!(limiter.min_interval("https://fast.example/a"), Duration::from_secs(2));
+
+        // config ช้ากว่าที่ site ขอ -> คง config
+        limiter.apply_crawl_delay("https://slow.example/robots.txt", Duration::from_secs(1));
+        assert_eq!(limiter.limit_for("slow.example").requests_per_second, 0.1);
+
+        // Crawl-delay: 0 ไม่เปลี่ยนอะไร
+        limiter.apply_crawl_delay("https://other.example/", Duration::ZERO);
+        let other = limiter.limit_for("other.example");
+        assert_eq!((other.requests_per_second, other.burst), (4.0, 5));
+    }
+
+    #[test]
+    fn apply_crawl_delay_limits_unlimited_hosts_and_existing_buckets() {
+        let limiter = RateLimiter::new(limit(0.0, 1), Vec::new());
+        assert_eq!(limiter.min_interval("https://example.com/"), Duration::ZERO);
+
+        let now = Instant::now();
+        limiter
+            .buckets
+            .lock()
+            .unwrap()
+            .insert("example.com".to_string(), TokenBucket::new(limit(0.0, 1), now));
+        limiter.apply_crawl_delay("https://example.com/", Duration::from_millis(250));
+        let mut buckets = limiter.buckets.lock().unwrap();
+        let bucket = buckets.get_mut("example.com").unwrap();
+        assert_eq!(bucket.reserve(now), Duration::ZERO);
+        assert_eq!(bucket.reserve(now), Duration::from_millis(250));
+    }
+}
@@ -0,0 +1,189 @@
+use spider::configuration::RedirectPolicy;
+use spider::page::Page;
+use spider::url::Url;
+use spider::website::Website;
+
+use crate::config::config::AppConfig;
+
+/// redirect ไป host อื่นยอมได้แค่ไหน
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CrossHostPolicy {
+    /// ยอมทุก host
+    Any,
+    /// ยอมเฉพาะ host ที่มี registrable domain เดียวกัน
+    /// เช่น example.com -> www.example.com, a.example.com -> b.example.com, a.example.co.uk -> example.co.uk
+    SameSite,
+    /// ยอมเฉพาะ host เดียวกันเท่านั้น (http -> https, เพิ่ม / ท้าย path, locale redirect)
+    SameHost,
+}
+
+impl CrossHostPolicy {
+    pub fn from_name(s: &str) -> Self {
+        match s {
+            "any" => CrossHostPolicy::Any,
+            "same_host" => CrossHostPolicy::SameHost,
+            _ => CrossHostPolicy::SameSite,
+        }
+    }
+}
+
+/// กฎการ follow redirect (ตั้งค่าได้ใน app.yaml)
+/// - max_redirects: จำนวน hop สูงสุดที่ follow (HttpRequest follow เองทีละ hop, Chrome ให้ spider follow)
+/// - cross_host: redirect ไป host อื่นแล้วยังใช้หน้านั้นได้หรือไม่
+#[derive(Debug, Clone, Copy)]
+pub struct RedirectRules {
+    pub max_redirects: usize,
+    pub cross_host: CrossHostPolicy,
+}
+
+impl RedirectRules {
+    pub fn from_config(cfg: &AppConfig) -> Self {
+        Self {
+            max_redirects: cfg.max_redirects.unwrap_or(5),
+            cross_host: CrossHostPolicy::from_name(cfg.cross_host_redirects.as_deref().unwrap_or("same_site")),
+        }
+    }
+
+    /// ตั้งจำนวน hop สูงสุดให้ Website ของ spider
+    pub fn apply(&self, website: &mut Website) {
+        website.with_redirect_limit(self.max_redirects);
+    }
+
+    /// ให้ spider คืน response 3xx แทนการ follow เอง (ใช้คู่กับ redirect_target เพื่อเก็บ redirect chain ทีละ hop)
+    pub fn apply_manual(&self, website: &mut Website) {
+        website.with_redirect_policy(RedirectPolicy::None);
+    }
+
+    /// ปลายทางของ redirect จาก url ไป final_url ผ่าน cross_host policy หรือไม่
+    pub fn allows(&self, url: &str, final_url: &str) -> bool {
+        let (Some(from), Some(to)) = (host_of(url), host_of(final_url)) else {
+            return true;
+        };
+        match self.cross_host {
+            CrossHostPolicy::Any => true,
+            CrossHostPolicy::SameHost => from == to,
+            CrossHostPolicy::SameSite => {
+                from == to || registrable_domain(&from).is_some_and(|site| registrable_domain(&to) == Some(site))
+            }
+        }
+    }
+}
+
+/// ปลายทางของ response 3xx (header Location resolve กับ url) None = ไม่ใช่ redirect หรือไม่มี Location
+pub fn redirect_target(url: &str, page: &Page) -> Option<String> {
+    if !page.status_code.is_redirection() {
+        return None;
+    }
+    let location = page.headers.as_ref()?.get("location")?.to_str().ok()?;
+    Url::parse(url).ok()?.join(location.trim()).ok().map(|u| u.to_string())
+}
+
+/// label ระดับสองที่ใช้เป็น suffix สาธารณะใต้ ccTLD (เช่น co.uk, co.th, com.au, ac.jp)
+const COUNTRY_SECOND_LEVELS: &[&str] = &[
+    "ac", "co", "com", "edu", "go", "gov", "in", "mi", "ne", "net", "or", "org",
+];
+
+/// registrable domain ของ host แบบประมาณ (ไม่ได้ใช้ Public Suffix List เต็ม)
+/// - 2 label สุดท้าย เช่น docs.example.com -> example.com
+/// - 3 label สุดท้ายเมื่อเป็น ccTLD (2 ตัวอักษร) และ label ก่อนหน้าอยู่ใน COUNTRY_SECOND_LEVELS
+///   เช่น www.example.co.th -> example.co.th
+/// - IP address หรือ host ที่เป็น suffix เอง (เช่น co.uk, localhost) -> None
+fn registrable_domain(host: &str) -> Option<&str> {
+    if host.parse::<std::net::IpAddr>().is_ok() || host.starts_with('[') {
+        return None;
+    }
+    let host = host.trim_end_matches('.');
+    let labels: Vec<&str> = host.split('.').collect();
+    let tld = labels.last()?;
+    let second = labels.len().checked_sub(2).map(|i| labels[i]);
+    let suffix_labels = match second {
+        Some(second) if tld.len() == 2 && COUNTRY_SECOND_LEVELS.contains(&second) => 2,
+        _ => 1,
+    };
+    if labels.len() <= suffix_labels {
+        return None;
+    }
+    let start: usize = labels[..labels.len() - suffix_labels - 1].iter().map(|l| l.len() + 1).sum();
+    Some(&host[start..])
+}
+
+fn host_of(url: &str) -> Option<String> {
+    Url::parse(url).ok()?.host_str().map(str::to_ascii_lowercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spider::reqwest::header::HeaderMap;
+    use spider::reqwest::StatusCode;
+
+    fn response(status: u16, location: Option<&str>) -> Page {
+        let mut headers = HeaderMap::new();
+        if let Some(location) = location {
+            headers.insert("location", location.parse().unwrap());
+        }
+        let mut page = Page::default();
+        page.status_code = StatusCode::from_u16(status).unwrap();
+        page.headers = Some(headers);
+        page
+    }
+
+    #[test]
+    fn redirect_target_resolves_location() {
+        let url = "http://example.com/docs/page";
+        assert_eq!(
+            redirect_target(url, &response(301, Some("https://example.com/docs/page"))).as_deref(),
+            Some("https://example.com/docs/page")
+        );
+        assert_eq!(
+            redirect_target(url, &response(302, Some("../th/page?x=1"))).as_deref(),
+            Some("http://example.com/th/page?x=1")
+        );
+        assert_eq!(redirect_target(url, &response(304, None)), None);
+        assert_eq!(redirect_target(url, &response(200, Some("/other"))), None);
+    }
+
+    #[test]
+    fn same_site_compares_registrable_domains() {
+        let rules = RedirectRules { max_redirects: 5, cross_host: CrossHostPolicy::SameSite };
+        assert!(rules.allows("https://example.com/a", "https://www.example.com/a"));
+        assert!(rules.allows("https://a.example.com/", "https://b.example.com/"));
+        assert!(rules.allows("https://www.example.co.uk/", "https://shop.example.co.uk/"));
+        assert!(!rules.allows("https://example.co.uk/", "https://co.uk/"));
+        assert!(!rules.allows("https://example.co.uk/", "https://other.co.uk/"));
+        assert!(!rules.allows("https://example.com/", "https://example.org/"));
+        assert!(!rules.allows("http://10.0.0.1/", "http://10.0.0.2/"));
+        assert_eq!(registrable_domain("a.b.example.com"), Some("example.com"));
+        assert_eq!(registrable_domain("localhost"), None);
+    }
+
+    #[test]
+    fn cross_host_policies() {
+        let rules = |cross_host| RedirectRules { max_redirects: 5, cross_host };
+        let same_host = rules(CrossHostPolicy::SameHost);
+        assert!(same_host.allows("http://example.com/a", "https://example.com/b"));
+        assert!(!same_host.allows("https://example.com/", "https://www.example.com/"));
+
+        let any = rules(CrossHostPolicy::Any);
+        assert!(any.allows("https://example.com/", "https://other.org/"));
+
+        let same_site = rules(CrossHostPolicy::SameSite);
+        assert!(same_site.allows("https://www.a.co.th/", "https://a.co.th/th/"));
+        assert!(!same_site.allows("https://a.co.th/", "https://b.co.th/"));
+        assert!(!same_site.allows("https://a.go.th/", "https://a.co.th/"));
+        // URL ที่ parse ไม่ได้ไม่ถือว่าข้าม host
+        assert!(same_host.allows("not a url", "https://example.com/"));
+    }
+
+    #[test]
+    fn registrable_domain_cases() {
+        assert_eq!(registrable_domain("a.co.th"), Some("a.co.th"));
+        assert_eq!(registrable_domain("www.a.co.th"), Some("a.co.th"));
+        assert_eq!(registrable_domain("co.th"), None);
+        assert_eq!(registrable_domain("shop.example.com.au"), Some("example.com.au"));
+        // co ใต้ TLD ที่ไม่ใช่ ccTLD ไม่ใช่ suffix
+        assert_eq!(registrable_domain("a.co.com"), Some("co.com"));
+        assert_eq!(registrable_domain("example.com."), Some("example.com"));
+        assert_eq!(registrable_domain("192.168.0.1"), None);
+    }
+}
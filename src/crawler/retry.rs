@@ -0,0 +1,216 @@
+use chrono::{DateTime, Utc};
+use spider::page::Page;
+use spider::website::Website;
+use tokio::time::{sleep, Duration};
+
+use super::rate_limiter::RateLimiter;
+use crate::config::config::AppConfig;
+
+/// นโยบาย retry ของการโหลดหน้าเว็บ, sitemap และ robots.txt
+/// - max_attempts: จำนวนครั้งที่ลองทั้งหมด (รวมครั้งแรก)
+/// - base_delay: delay ของการ retry ครั้งแรก เพิ่มเป็น 2 เท่าทุกครั้ง (exponential backoff + jitter)
+/// - max_delay: delay สูงสุดต่อครั้ง และ Retry-After ที่ยอมรอได้
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn from_config(cfg: &AppConfig) -> Self {
+        Self {
+            max_attempts: cfg.retry_max_attempts.unwrap_or(3).max(1),
+            base_delay: Duration::from_millis(cfg.retry_base_delay_ms.unwrap_or(500)),
+            max_delay: Duration::from_millis(cfg.retry_max_delay_ms.unwrap_or(30_000)),
+        }
+    }
+
+    /// delay ก่อนลองครั้งที่ attempt + 1 (equal jitter: ครึ่งหนึ่งคงที่ อีกครึ่งสุ่ม)
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay);
+        let half = exp / 2;
+        half + half.mul_f64(rand::random::<f64>())
+    }
+}
+
+/// status ที่ควรลองใหม่: 408, 425, 429 และ 5xx
+pub fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 408 | 425 | 429) || (500..600).contains(&status)
+}
+
+/// เหตุผลที่อ่านได้ของ status ที่ spider สร้างเองเมื่อไม่ได้ response จริง
+/// (spider ใส่ 521/522/523/524/525/598/599 แทน error ของการเชื่อมต่อ) status อื่นแสดงเป็น "HTTP <status>"
+pub fn status_reason(status: u16) -> String {
+    let cause = match status {
+        521 => "connection refused",
+        522 => "connection aborted",
+        523 => "connection reset",
+        524 => "timeout หรือเชื่อมต่อไม่ได้",
+        525 => "DNS resolve ไม่ได้",
+        598 | 599 => "network error",
+        _ => return format!("HTTP {}", status),
+    };
+    format!("{} (HTTP {})", cause, status)
+}
+
+/// อ่าน Retry-After (วินาที หรือ HTTP-date) จาก response headers
+fn retry_after(page: &Page) -> Option<Duration> {
+    let value = page
+        .headers
+        .as_ref()?
+        .get("retry-after")?
+        .to_str()
+        .ok()?
+        .trim()
+        .to_string();
+
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = DateTime::parse_from_rfc2822(&value).ok()?.with_timezone(&Utc);
+    Some((at - Utc::now()).to_std().unwrap_or(Duration::ZERO))
+}
+
+/// scrape URL เดียวด้วย spider พร้อม retry
+/// - make_website สร้าง Website ใหม่ทุกครั้งที่ลอง (spider เก็บผลไว้ใน Website)
+/// - ลองใหม่เมื่อไม่ได้ response หรือได้ status ที่ retry ได้ โดยเคารพ Retry-After บน 429/503
+/// - ถ้ายังได้ status ที่ retry ได้จนครบจำนวนครั้ง คืน page สุดท้ายให้ caller ตัดสินใจเอง
+/// - Err(reason) เมื่อไม่ได้ response เลย
+pub async fn scrape_with_retry<F>(
+    url: &str,
+    limiter: &RateLimiter,
+    policy: &RetryPolicy,
+    mut make_website: F,
+) -> Result<Page, String>
+where
+    F: FnMut() -> Website,
+{
+    let mut attempt = 1;
+    loop {
+        let mut website = make_website();
+        limiter.acquire(url).await;
+        website.scrape().await;
+
+        let page = website.get_pages().and_then(|p| p.first()).cloned();
+        let (reason, wait_hint) = match page {
+            Some(page) if !is_retryable_status(page.status_code.as_u16()) => return Ok(page),
+            Some(page) => {
+                let status = page.status_code.as_u16();
+                let hint = if status == 429 || status == 503 {
+                    retry_after(&page)
+                } else {
+                    None
+                };
+                if attempt >= policy.max_attempts {
+                    eprintln!("[retry] {} ยังได้ {} หลังลอง {} ครั้ง", url, status_reason(status), attempt);
+                    return Ok(page);
+                }
+                (status_reason(status), hint)
+            }
+            None => {
+                let reason = "ไม่ได้รับ response (timeout หรือเชื่อมต่อไม่ได้)".to_string();
+                if attempt >= policy.max_attempts {
+                    return Err(format!("{} หลังลอง {} ครั้ง", reason, attempt));
+                }
+                (reason, None)
+            }
+        };
+
+        let wait = match wait_hint {
+            Some(after) if after > policy.max_delay => {
+                return Err(format!(
+                    "{}: Retry-After {:?} เกิน retry_max_delay_ms ({:?})",
+                    reason, after, policy.max_delay
+                ));
+            }
+            Some(after) => after,
+            None => policy.backoff(attempt),
+        };
+        println!(
+            "[retry] {} ล้มเหลว ({}) -> ลองใหม่ครั้งที่ {}/{} ใน {:?}",
+            url,
+            reason,
+            attempt + 1,
+            policy.max_attempts,
+            wait
+        );
+        sleep(wait).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spider::reqwest::header::{HeaderMap, HeaderValue};
+
+    fn policy(base_ms: u64, max_ms: u64) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(base_ms),
+            max_delay: Duration::from_millis(max_ms),
+        }
+    }
+
+    fn page_with_retry_after(value: &str) -> Page {
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", HeaderValue::from_str(value).unwrap());
+        let mut page = Page::default();
+        page.headers = Some(headers);
+        page
+    }
+
+    #[test]
+    fn backoff_doubles_within_jitter_bounds_and_caps() {
+        let policy = policy(100, 1_000);
+        // attempt 1..=6 -> 100, 200, 400, 800, 1000 (cap), 1000
+        for (attempt, exp_ms) in [(1, 100), (2, 200), (3, 400), (4, 800), (5, 1_000), (6, 1_000), (40, 1_000)] {
+            let exp = Duration::from_millis(exp_ms);
+            for _ in 0..50 {
+                let delay = policy.backoff(attempt);
+                assert!(delay >= exp / 2 && delay <= exp, "attempt {}: {:?}", attempt, delay);
+            }
+        }
+        assert_eq!(RetryPolicy { base_delay: Duration::ZERO, ..policy }.backoff(3), Duration::ZERO);
+    }
+
+    #[test]
+    fn retryable_statuses() {
+        for status in [408, 425, 429, 500, 502, 503, 521, 524, 599] {
+            assert!(is_retryable_status(status), "{}", status);
+        }
+        for status in [200, 301, 304, 400, 403, 404, 410, 600] {
+            assert!(!is_retryable_status(status), "{}", status);
+        }
+    }
+
+    #[test]
+    fn spider_synthetic_statuses_are_described() {
+        assert_eq!(status_reason(521), "connection refused (HTTP 521)");
+        assert_eq!(status_reason(524), "timeout หรือเชื่อมต่อไม่ได้ (HTTP 524)");
+        assert_eq!(status_reason(525), "DNS resolve ไม่ได้ (HTTP 525)");
+        assert_eq!(status_reason(599), "network error (HTTP 599)");
+        assert_eq!(status_reason(503), "HTTP 503");
+    }
+
+    #[test]
+    fn retry_after_seconds_and_http_date() {
+        assert_eq!(retry_after(&page_with_retry_after(" 120 ")), Some(Duration::from_secs(120)));
+
+        let at = (Utc::now() + chrono::Duration::seconds(30)).to_rfc2822();
+        let wait = retry_after(&page_with_retry_after(&at)).unwrap();
+        assert!(wait > Duration::from_secs(25) && wait <= Duration::from_secs(30), "{:?}", wait);
+
+        // วันที่ที่ผ่านไปแล้วลองได้ทันที
+        let past = page_with_retry_after("Wed, 21 Oct 2015 07:28:00 GMT");
+        assert_eq!(retry_after(&past), Some(Duration::ZERO));
+
+        assert_eq!(retry_after(&page_with_retry_after("soon")), None);
+        assert_eq!(retry_after(&page_with_retry_after("-5")), None);
+        assert_eq!(retry_after(&Page::default()), None);
+    }
+}
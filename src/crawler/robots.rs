@@ -1,137 +1,141 @@
 use spider::url::Url;
 use spider::website::Website;
-use serde::Deserialize;
-use std::fs;
-use std::collections::HashSet;
-// load centralized app config for user_agent/delay/sitemap depth
-use crate::config::config::load_app_config;
-
-/// โหลด `robots.txt` จาก base_url และคืน Vec<String> ของ sitemap URLs
-pub async fn get_sitemaps_from_robots(
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crate::config::config::AppConfig;
+use super::front_matter::FrontMatter;
+use super::html_fetcher::{ContentDispatcher, FetchMode, FetchedPage};
+use super::link_rewriter::LinkMap;
+use super::page_filter::{PageFilter, SkipReason};
+use super::rate_limiter::RateLimiter;
+use super::retry::{scrape_with_retry, RetryPolicy};
+use super::robots_txt::RobotsTxt;
+use super::sitemap::{
+    decode_sitemap_body, find_feed_links, parse_sitemap, SitemapDocument, SitemapEntry, MAX_SITEMAP_BYTES,
+};
+
+/// โหลด `robots.txt` จาก base_url แล้ว parse เป็น RobotsTxt (กฎ Allow/Disallow + sitemap URLs)
+/// - 4xx หรือไม่มีไฟล์ -> อนุญาตทุก URL
+/// - 5xx -> ห้ามทุก URL (ตาม RFC 9309)
+/// - โหลดไม่ได้เลย (ไม่มี response หลัง retry) -> คืน Err ผู้เรียกต้องถือว่าห้ามทุก URL เช่นเดียวกับ 5xx
+pub async fn fetch_robots_txt(
     base_url: &str,
-) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    user_agent: &str,
+    limiter: &RateLimiter,
+    retry: &RetryPolicy,
+) -> Result<RobotsTxt, Box<dyn std::error::Error>> {
     let parsed = Url::parse(base_url)?;
     let robots_url = parsed.join("/robots.txt")?.to_string();
 
     println!("- กำลังโหลด: {}", robots_url);
 
-    let mut website = Website::new(&robots_url);
-    website.with_user_agent(Some("MyRustCrawler/1.0".into()));
-    website.with_depth(0);
-
-    // scrape() คืนค่า () เมื่อสำเร็จ
-    website.scrape().await;
+    let page = scrape_with_retry(&robots_url, limiter, retry, || single_page_website(&robots_url, user_agent)).await?;
 
-    let pages = website
-        .get_pages()
-        .ok_or("ไม่มีหน้าที่ถูกดาวน์โหลดจากเว็บไซต์")?;
-
-    let page = pages.first().ok_or("ไม่พบหน้าในเวกเตอร์ pages")?;
-    let content = page.get_html();
-
-    let sitemaps: Vec<String> = content
-        .lines()
-        .filter_map(|line| {
-            let t = line.trim();
-            if t.to_lowercase().starts_with("sitemap:") {
-                t.get(8..).map(|u| u.trim().to_string())
-            } else {
-                None
-            }
-        })
-        .collect();
+    if page.status_code.is_server_error() {
+        println!("[robots] {} ตอบ {} -> ถือว่าห้ามทุก URL", robots_url, page.status_code);
+        return Ok(RobotsTxt::disallow_all());
+    }
+    if page.status_code.is_client_error() {
+        println!("[robots] {} ตอบ {} -> ถือว่าอนุญาตทุก URL", robots_url, page.status_code);
+        return Ok(RobotsTxt::allow_all());
+    }
 
-    Ok(sitemaps)
+    Ok(RobotsTxt::parse(&page.get_html()))
 }
 
-/// ลองดึง sitemap.xml โดยตรงจาก https://<host>/sitemap.xml
-/// คืน Vec<String> ของ URL ที่เจอภายใน <loc> tags (และพิมพ์ออกมาทันทีเมื่อเจอ)
-pub async fn fetch_sitemap_direct(base_url: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    let parsed = Url::parse(base_url)?;
-    let sitemap_url = parsed.join("/sitemap.xml")?.to_string();
-
-    println!("- ลองโหลด sitemap ตรง ๆ: {}", sitemap_url);
+/// cache ของ robots.txt ต่อ origin (scheme://host:port)
+/// ใช้ตรวจ URL ก่อนโหลดทุกครั้ง โดยโหลด robots.txt ของแต่ละ host แค่ครั้งเดียว
+/// และส่ง Crawl-delay/Request-rate ของแต่ละ host ให้ rate limiter
+/// Crawl-delay ที่ยาวกว่า max_crawl_delay ถูกลดเหลือ max_crawl_delay
+/// ใช้ร่วมกันหลาย worker ได้ (lock เฉพาะตอนอ่าน/เขียน cache ไม่ถือ lock ระหว่างโหลด robots.txt)
+pub struct RobotsCache {
+    user_agent: String,
+    limiter: Arc<RateLimiter>,
+    retry: RetryPolicy,
+    max_crawl_delay: Duration,
+    entries: Mutex<HashMap<String, RobotsTxt>>,
+}
 
-    let mut website = Website::new(&sitemap_url);
-    // use centralized config values if present
-    let cfg = load_app_config();
-    let ua = cfg.user_agent.clone().unwrap_or_else(|| "MyRustCrawler/1.0".into());
-    let delay = cfg.delay_ms.unwrap_or(0);
-    let sitemap_max_depth = cfg.sitemap_max_depth.unwrap_or(5);
+impl RobotsCache {
+    pub fn new(user_agent: &str, limiter: Arc<RateLimiter>, retry: RetryPolicy, max_crawl_delay: Duration) -> Self {
+        Self {
+            user_agent: user_agent.to_string(),
+            limiter,
+            retry,
+            max_crawl_delay,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
 
-    website.with_user_agent(Some(ua.as_str()));
-    website.with_depth(0);
+    /// ใส่ robots.txt ที่โหลดมาแล้วของ url นั้น ๆ
+    pub fn insert(&self, url: &str, robots: RobotsTxt) {
+        if let Ok(parsed) = Url::parse(url) {
+            let origin = parsed.origin().ascii_serialization();
+            self.apply_crawl_delay(&origin, &robots);
+            self.entries.lock().unwrap_or_else(|e| e.into_inner()).insert(origin, robots);
+        }
+    }
 
-    website.configuration.delay = delay;
-    website.scrape().await;
+    /// ตรวจว่า url ได้รับอนุญาตตาม robots.txt ของ host นั้นหรือไม่
+    /// ถ้ายังไม่มีใน cache จะโหลด robots.txt ของ host ก่อน (worker ที่ตรวจ host อื่นไม่ต้องรอ)
+    pub async fn is_allowed(&self, url: &str) -> bool {
+        let Ok(parsed) = Url::parse(url) else {
+            return true;
+        };
+        let origin = parsed.origin().ascii_serialization();
 
-    let pages = website.get_pages();
-    if pages.is_none() || pages.as_ref().unwrap().is_empty() {
-        // ไม่พบหรือดึงไม่ได้
-        return Ok(Vec::new());
-    }
+        if let Some(robots) = self.entries.lock().unwrap_or_else(|e| e.into_inner()).get(&origin) {
+            return robots.is_allowed(&self.user_agent, url);
+        }
 
-    let page = pages.unwrap().first().ok_or("ไม่พบหน้าในเวกเตอร์ pages")?;
-    let content = page.get_html();
-
-    // หา <loc> ... </loc> แบบไม่ขึ้นกับ case
-    let mut nested_sitemaps: Vec<String> = Vec::new();
-    let mut page_urls: Vec<String> = Vec::new();
-    let content_lower = content.to_lowercase();
-    let mut pos = 0usize;
-    while let Some(start_rel) = content_lower[pos..].find("<loc") {
-        let start = pos + start_rel;
-        // หาตำแหน่ง '>' ของแท็กเปิด
-        if let Some(gt_rel) = content_lower[start..].find('>') {
-            let content_start = start + gt_rel + 1;
-            // หาตัวปิด </loc>
-            if let Some(end_rel) = content_lower[content_start..].find("</loc>") {
-                let content_end = content_start + end_rel;
-                let url_text = content[content_start..content_end].trim().to_string();
-                if !url_text.is_empty() {
-                    // ถ้าเป็น sitemap index (.xml) ให้เก็บไปโหลดแบบ recursive
-                    if url_text.ends_with(".xml") || url_text.contains(".xml?") {
-                        println!("-> พบ sitemap index: {}", url_text);
-                        nested_sitemaps.push(url_text);
-                    } else {
-                        println!("-> พบ URL ใน sitemap.xml: {}", url_text);
-                        page_urls.push(url_text);
-                    }
-                }
-                pos = content_end + 6; // ข้าม "</loc>"
-                continue;
+        let robots = match fetch_robots_txt(url, &self.user_agent, &self.limiter, &self.retry).await {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("[robots] โหลด robots.txt ของ {} ไม่ได้: {:?} -> ถือว่าห้ามทุก URL (RFC 9309)", origin, e);
+                RobotsTxt::disallow_all()
             }
-        }
-        break;
+        };
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        // worker อื่นอาจโหลด host เดียวกันเสร็จก่อน ใช้ของที่อยู่ใน cache แล้ว
+        let robots = entries.entry(origin).or_insert_with_key(|origin| {
+            self.apply_crawl_delay(origin, &robots);
+            robots
+        });
+        robots.is_allowed(&self.user_agent, url)
     }
 
-    // ถ้ามี nested sitemaps ให้โหลดแบบ recursive เพื่อรวบรวมหน้า
-    if !nested_sitemaps.is_empty() {
-        let mut visited = HashSet::new();
-        for s in nested_sitemaps {
-            match fetch_sitemap_recursive(&s, &ua, delay, &mut visited, 0, sitemap_max_depth).await {
-                Ok(mut urls) => page_urls.append(&mut urls),
-                Err(e) => eprintln!("-> ไม่สามารถโหลด nested sitemap {}: {:?}", s, e),
+    fn apply_crawl_delay(&self, origin: &str, robots: &RobotsTxt) {
+        if let Some(mut delay) = robots.crawl_delay(&self.user_agent) {
+            if delay > self.max_crawl_delay {
+                eprintln!(
+                    "[robots] {} ขอ delay {:?} ต่อ request เกิน max_crawl_delay_ms -> ใช้ {:?} แทน",
+                    origin, delay, self.max_crawl_delay
+                );
+                delay = self.max_crawl_delay;
             }
+            println!("[robots] {} ขอ delay {:?} ต่อ request", origin, delay);
+            self.limiter.apply_crawl_delay(origin, delay);
         }
     }
-
-    Ok(page_urls)
 }
 
 /// โหลด sitemap แบบ recursive - รองรับ sitemap index (nested)
-/// ใช้ config จาก AppConfig (user_agent, delay_ms)
-/// - ถ้า <loc> ชี้ไปที่ .xml -> โหลดต่อแบบ recursive
-/// - ถ้า <loc> เป็น URL ปกติ -> เก็บไว้
-/// คืนค่า Vec<String> ของ URL ทั้งหมด (ไม่ซ้ำ)
+/// ใช้ user_agent จาก AppConfig และ rate limiter ที่ใช้ร่วมกับ fetcher อื่น
+/// - root เป็น <sitemapindex> -> โหลด <loc> แต่ละตัวต่อแบบ recursive
+/// - root เป็น <urlset> -> เก็บ entry (loc, lastmod, changefreq, priority) ไว้
+/// - RSS/Atom feed และ text sitemap -> เก็บ URL ของแต่ละ item/บรรทัด
+///
+/// คืนค่า Vec<SitemapEntry> ของหน้าทั้งหมด
 pub async fn fetch_sitemap_recursive(
     sitemap_url: &str,
     user_agent: &str,
-    delay_ms: u64,
+    limiter: &RateLimiter,
+    retry: &RetryPolicy,
     visited: &mut HashSet<String>,
     depth: usize,
     max_depth: usize,
-) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+) -> Result<Vec<SitemapEntry>, Box<dyn std::error::Error>> {
     // ป้องกัน infinite loop และ depth เกิน
     if visited.contains(sitemap_url) || depth > max_depth {
         return Ok(Vec::new());
@@ -140,131 +144,154 @@ pub async fn fetch_sitemap_recursive(
 
     println!("[sitemap][depth={}] กำลังโหลด: {}", depth, sitemap_url);
 
-    let mut website = Website::new(sitemap_url);
-    website.with_user_agent(Some(user_agent.into()));
-    website.with_depth(0);
-    website.configuration.delay = delay_ms;
-
-    website.scrape().await;
-
-    let pages = website.get_pages();
-    if pages.is_none() || pages.as_ref().unwrap().is_empty() {
-        println!("[sitemap][depth={}] ไม่พบหน้าที่ดาวน์โหลดได้", depth);
+    let page = match scrape_with_retry(sitemap_url, limiter, retry, || single_page_website(sitemap_url, user_agent)).await {
+        Ok(page) => page,
+        Err(reason) => {
+            eprintln!("[sitemap][depth={}] โหลด {} ไม่สำเร็จ: {}", depth, sitemap_url, reason);
+            return Ok(Vec::new());
+        }
+    };
+    if !page.status_code.is_success() {
+        eprintln!("[sitemap][depth={}] {} ตอบ HTTP {}", depth, sitemap_url, page.status_code);
         return Ok(Vec::new());
     }
 
-    let page = pages.unwrap().first().ok_or("ไม่พบหน้าในเวกเตอร์ pages")?;
-    let content = page.get_html();
-
-    // หา <loc> ... </loc>
-    let mut sitemap_urls = Vec::new();
-    let mut page_urls = Vec::new();
-    
-    let content_lower = content.to_lowercase();
-    let mut pos = 0usize;
-    
-    while let Some(start_rel) = content_lower[pos..].find("<loc") {
-        let start = pos + start_rel;
-        if let Some(gt_rel) = content_lower[start..].find('>') {
-            let content_start = start + gt_rel + 1;
-            if let Some(end_rel) = content_lower[content_start..].find("</loc>") {
-                let content_end = content_start + end_rel;
-                let url_text = content[content_start..content_end].trim().to_string();
-                
-                if !url_text.is_empty() {
-                    // ตรวจสอบว่าเป็น sitemap (.xml) หรือ URL ปกติ
-                    if url_text.ends_with(".xml") || url_text.contains(".xml?") {
-                        println!("[sitemap][depth={}] -> พบ sitemap nested: {}", depth, url_text);
-                        sitemap_urls.push(url_text);
-                    } else {
-                        println!("[sitemap][depth={}] -> พบ URL: {}", depth, url_text);
-                        page_urls.push(url_text);
-                    }
-                }
-                pos = content_end + 6;
-                continue;
+    let content_encoding = page
+        .headers
+        .as_ref()
+        .and_then(|h| h.get("content-encoding"))
+        .and_then(|v| v.to_str().ok());
+
+    // รองรับ sitemap.xml.gz (ดูจากนามสกุล, magic bytes และ Content-Encoding)
+    let content = match decode_sitemap_body(
+        sitemap_url,
+        page.get_html_bytes_u8(),
+        content_encoding,
+        MAX_SITEMAP_BYTES,
+    ) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("[sitemap][depth={}] อ่าน body ของ {} ไม่ได้: {:?}", depth, sitemap_url, e);
+            return Ok(Vec::new());
+        }
+    };
+
+    let (nested_sitemaps, mut page_entries) = match parse_sitemap(&content) {
+        Ok(SitemapDocument::Index(children)) => {
+            for child in &children {
+                println!("[sitemap][depth={}] -> พบ sitemap nested: {}", depth, child.loc);
             }
+            (children, Vec::new())
         }
-        break;
-    }
+        Ok(SitemapDocument::UrlSet(entries)) => {
+            for entry in &entries {
+                println!("[sitemap][depth={}] -> พบ URL: {}", depth, entry.loc);
+            }
+            (Vec::new(), entries)
+        }
+        Ok(SitemapDocument::Feed(entries)) => {
+            for entry in &entries {
+                println!("[sitemap][depth={}] -> พบ URL จาก feed: {}", depth, entry.loc);
+            }
+            (Vec::new(), entries)
+        }
+        Ok(SitemapDocument::Text(entries)) => {
+            for entry in &entries {
+                println!("[sitemap][depth={}] -> พบ URL จาก text sitemap: {}", depth, entry.loc);
+            }
+            (Vec::new(), entries)
+        }
+        Ok(SitemapDocument::Unknown(root)) => {
+            println!("[sitemap][depth={}] root element <{}> ไม่ใช่ sitemap หรือ feed", depth, root);
+            (Vec::new(), Vec::new())
+        }
+        Err(e) => {
+            eprintln!("[sitemap][depth={}] parse XML ไม่สำเร็จ {}: {:?}", depth, sitemap_url, e);
+            (Vec::new(), Vec::new())
+        }
+    };
 
     // Recursive: โหลด sitemap ที่ซ้อนกัน (ใช้ Box::pin เพื่อหลีกเลี่ยง infinite size)
-    for nested_sitemap in sitemap_urls {
+    for nested_sitemap in nested_sitemaps {
         let result = Box::pin(fetch_sitemap_recursive(
-            &nested_sitemap,
+            &nested_sitemap.loc,
             user_agent,
-            delay_ms,
+            limiter,
+            retry,
             visited,
             depth + 1,
             max_depth,
         )).await;
         
         match result {
-            Ok(mut nested_urls) => {
-                page_urls.append(&mut nested_urls);
+            Ok(mut nested_entries) => {
+                page_entries.append(&mut nested_entries);
             }
             Err(e) => {
-                eprintln!("[sitemap][depth={}] ไม่สามารถโหลด {} ได้: {:?}", depth, nested_sitemap, e);
+                eprintln!("[sitemap][depth={}] ไม่สามารถโหลด {} ได้: {:?}", depth, nested_sitemap.loc, e);
             }
         }
     }
 
-    Ok(page_urls)
+    Ok(page_entries)
 }
 
-/// Config สำหรับการ crawl แบบ native โดย spider
-#[derive(Debug, Deserialize)]
-struct SpiderConfig {
-    depth: Option<usize>,
-    user_agent: Option<String>,
-    delay_ms: Option<u64>,
-    max_pages: Option<usize>,
-    native_download_mode: Option<String>, // <- NEW
-}
+/// โหลดหน้าแรกของ base_url แล้วหา feed จาก `<link rel="alternate" type="application/rss+xml">`
+/// (และ Atom) คืน URL ของ feed ที่เจอ
+pub async fn discover_feeds(
+    base_url: &str,
+    user_agent: &str,
+    limiter: &RateLimiter,
+    retry: &RetryPolicy,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    println!("- กำลังหา RSS/Atom feed จากหน้าแรก: {}", base_url);
 
-impl Default for SpiderConfig {
-    fn default() -> Self {
-        Self {
-            depth: Some(3),
-            user_agent: Some("MyRustCrawler/1.0".into()),
-            delay_ms: Some(250),
-            max_pages: Some(200),
-            native_download_mode: Some("HttpRequest".into()), // <- NEW
-        }
+    let page = scrape_with_retry(base_url, limiter, retry, || single_page_website(base_url, user_agent)).await?;
+
+    let feeds = find_feed_links(base_url, &page.get_html());
+    for feed in &feeds {
+        println!("-> พบ feed: {}", feed);
     }
+    Ok(feeds)
 }
 
-/// ถ้าไม่มี robots.txt และไม่มี sitemap.xml -> ใช้ spider native crawl
-/// จะอ่าน config จาก "config/app.yaml" (ถ้าไฟล์มี) แล้วเริ่ม crawl จากหน้าแรกของ base_url
-pub async fn crawl_with_spider(base_url: &str) -> Result<(), Box<dyn std::error::Error>> {
-    // โหลด config จากไฟล์ ถ้ามี
-    let cfg: SpiderConfig = match fs::read_to_string("src/config/app.yaml") {
-        Ok(s) => match serde_yaml::from_str(&s) {
-            Ok(parsed) => parsed,
-            Err(e) => {
-                eprintln!("-> ไม่สามารถ parse config/app.yaml: {}  -> ใช้ค่า default", e);
-                SpiderConfig::default()
-            }
-        },
-        Err(_) => {
-            println!("-> ไม่พบ config/app.yaml, ใช้ค่า default");
-            SpiderConfig::default()
-        }
-    };
+/// Website ของ spider ที่โหลดแค่ URL เดียว (depth 0) สำหรับ robots.txt, sitemap และ feed
+fn single_page_website(url: &str, user_agent: &str) -> Website {
+    let mut website = Website::new(url);
+    website.with_user_agent(Some(user_agent));
+    website.with_depth(0);
+    website
+}
 
+/// ถ้าไม่มี robots.txt และไม่มี sitemap.xml -> ใช้ spider native crawl
+/// ใช้ depth/user_agent/front_matter จาก AppConfig ที่ crawl โหลดไว้แล้ว แล้วเริ่ม crawl จากหน้าแรกของ base_url
+/// spider ยิง request เองจึงเรียก limiter.acquire ต่อหน้าไม่ได้ ใช้ระยะห่างของ host จาก limiter เป็น delay แทน
+/// (รวมค่าเฉพาะโดเมนและ Crawl-delay ของ robots.txt แต่ไม่มี burst)
+/// retry ทำโดย spider ตามจำนวนครั้งของ retry policy แต่ไม่มี backoff และไม่อ่าน Retry-After
+/// ไฟล์ที่บันทึกถูกจดลง written
+/// ทุกหน้าผ่าน page_filter และแปลงด้วย handler ตาม content type เหมือนหน้าจาก sitemap
+pub async fn crawl_with_spider(
+    base_url: &str,
+    cfg: &AppConfig,
+    limiter: &RateLimiter,
+    retry: &RetryPolicy,
+    page_filter: &PageFilter,
+    dispatcher: &ContentDispatcher,
+    written: &mut LinkMap,
+) -> Result<(), Box<dyn std::error::Error>> {
     println!("- เริ่ม native spider crawl ที่: {}", base_url);
-    println!("- config: depth={:?}, user_agent={:?}, delay_ms={:?}, max_pages={:?}, fetch_mode={:?}",
-        cfg.depth, cfg.user_agent, cfg.delay_ms, cfg.max_pages, cfg.native_download_mode);
+    let delay = limiter.min_interval(base_url);
+    println!("- config: depth={:?}, user_agent={:?}, delay={:?}, max_pages={:?}, fetch_mode={:?}",
+        cfg.depth, cfg.user_agent, delay, cfg.max_pages, cfg.native_download_mode);
 
     let mut website = Website::new(base_url);
     website.with_user_agent(cfg.user_agent.as_deref());
+    website.with_respect_robots_txt(true);
     if let Some(d) = cfg.depth {
         website.with_depth(d);
     }
-    // ตั้งค่า delay ถ้า config มี (ใช้ u64 โดยตรง)
-    if let Some(ms) = cfg.delay_ms {
-        website.configuration.delay = ms;
-    }
+    website.with_delay(delay.as_millis() as u64);
+    website.with_retry(retry.max_attempts.saturating_sub(1).min(u8::MAX as u32) as u8);
 
     website.scrape().await;
 
@@ -277,18 +304,41 @@ pub async fn crawl_with_spider(base_url: &str) -> Result<(), Box<dyn std::error:
         for (idx, page) in pages.iter().enumerate() {
             let current = idx + 1;
             let percent = if total > 0.0 { (current as f64 / total) * 100.0 } else { 0.0 };
-            let url = page.get_url().to_string();
-            let html = page.get_html();
+            // native crawl ไม่ได้วัดเวลาแยกต่อหน้า
+            let fetched = FetchedPage::from_spider_page(page.get_url(), page, FetchMode::HttpRequest, Duration::ZERO);
+            let url = fetched.url.clone();
             
             println!("\n[{}/{}] ({:.1}%) Processing: {}", current, pages.len(), percent, url);
-            println!("-> visited: {} ({} bytes HTML)", url, html.len());
-            
-            // Convert to markdown
-            let markdown = super::html_to_markdown::html_to_markdown(&url, &html);
+            println!("-> visited: {} (HTTP {}, {}, {} bytes)", url, fetched.status, fetched.content_type, fetched.bytes.len());
+
+            // ข้าม error page, soft-404, เนื้อหาว่าง และ content type ที่ไม่มี handler
+            let handler = dispatcher.handler_for(&fetched.content_type);
+            let reason = page_filter
+                .check(&fetched)
+                .or_else(|| handler.is_none().then(|| SkipReason::ContentType(fetched.content_type.clone())));
+            let (None, Some(handler)) = (reason.as_ref(), handler) else {
+                let reason = reason.map(|r| r.to_string()).unwrap_or_default();
+                println!("⏭ ข้าม {}: {} — {:.1}%", url, reason, percent);
+                continue;
+            };
+
+            // Convert to markdown ด้วย handler ตาม content type
+            let markdown = match handler.to_markdown(&fetched) {
+                Ok(markdown) => markdown,
+                Err(err) => {
+                    eprintln!("✗ แปลง {} ด้วย {} ไม่สำเร็จ: {:?} — {:.1}%", url, handler.name(), err, percent);
+                    continue;
+                }
+            };
             
-            // Save immediately
-            match super::markdown_writer::write_markdown_file(&url, &markdown) {
-                Ok(path) => println!("✓ บันทึกแล้ว: {} — {:.1}%", path.display(), percent),
+            // Save immediately (native crawl ไม่มี lastmod จาก sitemap)
+            let front_matter = cfg.front_matter.unwrap_or(false).then(|| FrontMatter::from_page(&fetched, None));
+            let path = written.reserve(&fetched);
+            match super::markdown_writer::write_markdown_file(&path, &markdown, front_matter.as_ref()) {
+                Ok(()) => {
+                    println!("✓ บันทึกแล้ว: {} — {:.1}%", path.display(), percent);
+                    written.record(&fetched, &path);
+                }
                 Err(err) => eprintln!("✗ บันทึกไม่สำเร็จ {}: {:?} — {:.1}%", url, err, percent),
             }
         }
@@ -0,0 +1,365 @@
+use spider::url::Url;
+use std::time::Duration;
+
+/// กฎ Allow/Disallow หนึ่งบรรทัดจาก robots.txt
+#[derive(Debug, Clone)]
+struct RobotsRule {
+    allow: bool,
+    pattern: String,
+}
+
+/// กลุ่มของ user-agent ที่ใช้กฎชุดเดียวกัน
+#[derive(Debug, Clone, Default)]
+struct RobotsGroup {
+    user_agents: Vec<String>,
+    rules: Vec<RobotsRule>,
+    crawl_delay: Option<Duration>,
+}
+
+/// robots.txt ที่ parse แล้วของ host หนึ่ง
+/// - groups: กฎแยกตาม user-agent
+/// - sitemaps: บรรทัด `Sitemap:` ทั้งหมด (ไม่ขึ้นกับ group)
+#[derive(Debug, Clone, Default)]
+pub struct RobotsTxt {
+    groups: Vec<RobotsGroup>,
+    disallow_all: bool,
+    pub sitemaps: Vec<String>,
+}
+
+impl RobotsTxt {
+    /// robots.txt ที่อนุญาตทุก URL (ใช้เมื่อไม่มีไฟล์หรือได้ 4xx)
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    /// robots.txt ที่ห้ามทุก URL (ใช้เมื่อ server ตอบ 5xx)
+    pub fn disallow_all() -> Self {
+        Self {
+            disallow_all: true,
+            ..Self::default()
+        }
+    }
+
+    /// parse เนื้อหา robots.txt ตาม RFC 9309
+    /// - บรรทัด user-agent ที่ติดกันเป็นกลุ่มเดียวกัน
+    /// - บรรทัด user-agent ที่ตามหลังกฎ จะเริ่มกลุ่มใหม่
+    pub fn parse(content: &str) -> Self {
+        let mut robots = Self::default();
+        let mut current: Option<RobotsGroup> = None;
+        let mut in_rules = false;
+
+        for raw_line in content.lines() {
+            let line = raw_line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_ascii_lowercase();
+            let value = value.trim();
+
+            match key.as_str() {
+                "user-agent" => {
+                    if in_rules || current.is_none() {
+                        if let Some(group) = current.take() {
+                            robots.groups.push(group);
+                        }
+                        current = Some(RobotsGroup::default());
+                        in_rules = false;
+                    }
+                    if let Some(group) = current.as_mut() {
+                        group.user_agents.push(normalize_agent(value));
+                    }
+                }
+                "allow" | "disallow" => {
+                    // กฎที่อยู่นอก group (ไม่มี user-agent นำหน้า) ถูกละไว้
+                    let Some(group) = current.as_mut() else {
+                        continue;
+                    };
+                    in_rules = true;
+                    // `Disallow:` ว่าง ๆ หมายถึงไม่มีข้อห้าม
+                    if value.is_empty() {
+                        continue;
+                    }
+                    group.rules.push(RobotsRule {
+                        allow: key == "allow",
+                        pattern: normalize_pattern(value),
+                    });
+                }
+                "crawl-delay" | "request-rate" => {
+                    let Some(group) = current.as_mut() else {
+                        continue;
+                    };
+                    in_rules = true;
+                    let delay = if key == "crawl-delay" {
+                        parse_crawl_delay(value)
+                    } else {
+                        parse_request_rate(value)
+                    };
+                    // ถ้ามีทั้ง Crawl-delay และ Request-rate ใช้ค่าที่เข้มกว่า
+                    if let Some(d) = delay {
+                        group.crawl_delay = Some(group.crawl_delay.map_or(d, |cur| cur.max(d)));
+                    }
+                }
+                "sitemap" => {
+                    if !value.is_empty() {
+                        robots.sitemaps.push(value.to_string());
+                    }
+                }
+                _ => {
+                    // directive อื่น ๆ ยังถือว่าอยู่ในส่วนกฎของ group
+                    if current.is_some() {
+                        in_rules = true;
+                    }
+                }
+            }
+        }
+
+        if let Some(group) = current.take() {
+            robots.groups.push(group);
+        }
+        robots
+    }
+
+    /// ตรวจว่า user_agent นี้ได้รับอนุญาตให้โหลด url หรือไม่
+    /// ใช้กฎที่ pattern ยาวที่สุด ถ้ายาวเท่ากัน Allow ชนะ
+    pub fn is_allowed(&self, user_agent: &str, url: &str) -> bool {
+        let path = match Url::parse(url) {
+            Ok(parsed) => {
+                let mut p = parsed.path().to_string();
+                if let Some(q) = parsed.query() {
+                    p.push('?');
+                    p.push_str(q);
+                }
+                p
+            }
+            Err(_) => url.to_string(),
+        };
+
+        // robots.txt เองต้องโหลดได้เสมอ
+        if path == "/robots.txt" {
+            return true;
+        }
+        if self.disallow_all {
+            return false;
+        }
+
+        let mut best: Option<&RobotsRule> = None;
+        for rule in self.rules_for(user_agent) {
+            if !pattern_matches(&rule.pattern, &path) {
+                continue;
+            }
+            best = match best {
+                Some(b) if b.pattern.len() > rule.pattern.len() => Some(b),
+                Some(b) if b.pattern.len() == rule.pattern.len() && b.allow => Some(b),
+                _ => Some(rule),
+            };
+        }
+        best.map(|r| r.allow).unwrap_or(true)
+    }
+
+    /// delay ระหว่าง request ที่ site ขอ (Crawl-delay / Request-rate) สำหรับ user_agent นี้
+    pub fn crawl_delay(&self, user_agent: &str) -> Option<Duration> {
+        self.groups_for(user_agent)
+            .into_iter()
+            .filter_map(|g| g.crawl_delay)
+            .max()
+    }
+
+    fn rules_for(&self, user_agent: &str) -> Vec<&RobotsRule> {
+        self.groups_for(user_agent)
+            .into_iter()
+            .flat_map(|g| g.rules.iter())
+            .collect()
+    }
+
+    /// group ทั้งหมดที่ตรงกับ product token ของเรา
+    /// ถ้าไม่มี group ไหนตรงเลย ใช้ group `*`
+    fn groups_for(&self, user_agent: &str) -> Vec<&RobotsGroup> {
+        let token = normalize_agent(user_agent);
+        let specific: Vec<&RobotsGroup> = self
+            .groups
+            .iter()
+            .filter(|g| g.user_agents.iter().any(|ua| ua != "*" && *ua == token))
+            .collect();
+
+        if specific.is_empty() {
+            self.groups
+                .iter()
+                .filter(|g| g.user_agents.iter().any(|ua| ua == "*"))
+                .collect()
+        } else {
+            specific
+        }
+    }
+}
+
+/// ตัด version และช่องว่างออกจาก user-agent เช่น "SSS/1.0 (bot)" -> "sss"
+fn normalize_agent(agent: &str) -> String {
+    agent
+        .trim()
+        .split(|c: char| c == '/' || c.is_whitespace())
+        .next()
+        .unwrap_or("")
+        .to_ascii_lowercase()
+}
+
+/// `Crawl-delay: 2.5` -> 2.5 วินาที
+/// ค่าติดลบ, NaN หรือใหญ่เกิน Duration ถือว่าไม่ได้ระบุ (เพดานจริงคุมด้วย max_crawl_delay_ms ใน RobotsCache)
+fn parse_crawl_delay(value: &str) -> Option<Duration> {
+    let secs: f64 = value.split_whitespace().next()?.parse().ok()?;
+    Duration::try_from_secs_f64(secs).ok()
+}
+
+/// `Request-rate: 1/5` (1 request ต่อ 5 วินาที) -> 5 วินาทีต่อ request
+/// รองรับหน่วย s/m/h ท้ายตัวเลข เช่น `1/10m` และไม่สนใจช่วงเวลาที่ต่อท้าย
+fn parse_request_rate(value: &str) -> Option<Duration> {
+    let rate = value.split_whitespace().next()?;
+    let (requests, period) = rate.split_once('/')?;
+    let requests: f64 = requests.trim().parse().ok()?;
+
+    let period = period.trim().to_ascii_lowercase();
+    let (number, unit_secs) = match period.chars().last()? {
+        's' => (&period[..period.len() - 1], 1.0),
+        'm' => (&period[..period.len() - 1], 60.0),
+        'h' => (&period[..period.len() - 1], 3600.0),
+        _ => (period.as_str(), 1.0),
+    };
+    let seconds: f64 = number.parse::<f64>().ok()? * unit_secs;
+
+    if requests > 0.0 {
+        Duration::try_from_secs_f64(seconds / requests).ok()
+    } else {
+        None
+    }
+}
+
+/// percent-encode อักขระ non-ASCII ใน pattern ให้ตรงกับ path ที่ Url::parse คืนมา
+fn normalize_pattern(pattern: &str) -> String {
+    let mut out = String::with_capacity(pattern.len());
+    let bytes = pattern.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if b == b'%' && i + 2 < bytes.len() && bytes[i + 1].is_ascii_hexdigit() && bytes[i + 2].is_ascii_hexdigit() {
+            out.push('%');
+            out.push(bytes[i + 1].to_ascii_uppercase() as char);
+            out.push(bytes[i + 2].to_ascii_uppercase() as char);
+            i += 3;
+            continue;
+        }
+        if b.is_ascii() {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+        i += 1;
+    }
+    out
+}
+
+/// จับคู่ pattern กับ path
+/// - `*` แทนอักขระใด ๆ กี่ตัวก็ได้
+/// - `$` ท้าย pattern บังคับให้จบที่ท้าย path
+/// - นอกนั้นถือเป็น prefix match
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let pat = pattern.as_bytes();
+    let path = path.as_bytes();
+
+    // ตำแหน่งใน path ที่ prefix ของ pattern จับได้ถึง (เรียงจากน้อยไปมาก)
+    let mut positions: Vec<usize> = vec![0];
+    for (i, &c) in pat.iter().enumerate() {
+        if c == b'$' && i + 1 == pat.len() {
+            return positions.last() == Some(&path.len());
+        }
+        if c == b'*' {
+            positions = (positions[0]..=path.len()).collect();
+        } else {
+            positions = positions
+                .into_iter()
+                .filter(|&p| p < path.len() && path[p] == c)
+                .map(|p| p + 1)
+                .collect();
+            if positions.is_empty() {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crawl_delay_parses_seconds_and_request_rate() {
+        let robots = RobotsTxt::parse("User-agent: *\nCrawl-delay: 2.5\nRequest-rate: 1/10m\n");
+        assert_eq!(robots.crawl_delay("SSS/1.0"), Some(Duration::from_secs(600)));
+
+        let robots = RobotsTxt::parse("User-agent: *\nCrawl-delay: 2.5\n");
+        assert_eq!(robots.crawl_delay("SSS/1.0"), Some(Duration::from_millis(2500)));
+    }
+
+    #[test]
+    fn out_of_range_crawl_delay_is_ignored() {
+        for content in [
+            "User-agent: *\nCrawl-delay: 99999999999999999999\n",
+            "User-agent: *\nRequest-rate: 1/99999999999999999h\n",
+            "User-agent: *\nCrawl-delay: -1\n",
+            "User-agent: *\nCrawl-delay: NaN\n",
+            "User-agent: *\nRequest-rate: 0/5\n",
+        ] {
+            assert_eq!(RobotsTxt::parse(content).crawl_delay("SSS/1.0"), None, "{:?}", content);
+        }
+    }
+
+    #[test]
+    fn allow_wins_over_disallow_of_equal_length() {
+        let robots = RobotsTxt::parse("User-agent: *\nDisallow: /page\nAllow: /page\nDisallow: /docs/private\nAllow: /docs\n");
+        assert!(robots.is_allowed("SSS/1.0", "https://e.com/page"));
+        assert!(robots.is_allowed("SSS/1.0", "https://e.com/docs/a"));
+        // pattern ที่ยาวกว่าชนะ
+        assert!(!robots.is_allowed("SSS/1.0", "https://e.com/docs/private/a"));
+    }
+
+    #[test]
+    fn wildcard_and_end_anchor() {
+        let robots = RobotsTxt::parse("User-agent: *\nDisallow: /*.pdf$\n");
+        assert!(!robots.is_allowed("SSS/1.0", "https://e.com/x.pdf"));
+        assert!(!robots.is_allowed("SSS/1.0", "https://e.com/a/b.pdf"));
+        assert!(robots.is_allowed("SSS/1.0", "https://e.com/x.pdf?q=1"));
+        assert!(robots.is_allowed("SSS/1.0", "https://e.com/x.pdf.html"));
+
+        let robots = RobotsTxt::parse("User-agent: *\nDisallow: /\nAllow: /$\n");
+        assert!(robots.is_allowed("SSS/1.0", "https://e.com/"));
+        assert!(!robots.is_allowed("SSS/1.0", "https://e.com/page"));
+        // robots.txt เองโหลดได้เสมอ
+        assert!(robots.is_allowed("SSS/1.0", "https://e.com/robots.txt"));
+    }
+
+    #[test]
+    fn own_agent_group_replaces_star_group() {
+        let robots = RobotsTxt::parse(
+            "User-agent: *\nDisallow: /\n\nUser-agent: OtherBot\nUser-agent: sss\nDisallow: /private\n",
+        );
+        assert!(robots.is_allowed("SSS/1.0", "https://e.com/page"));
+        assert!(!robots.is_allowed("SSS/1.0", "https://e.com/private/a"));
+        assert!(!robots.is_allowed("Googlebot/2.1", "https://e.com/page"));
+    }
+
+    #[test]
+    fn non_ascii_paths_match_percent_encoded() {
+        for rule in ["Disallow: /ข่าว", "Disallow: /%e0%b8%82%e0%b9%88%e0%b8%b2%e0%b8%a7"] {
+            let robots = RobotsTxt::parse(&format!("User-agent: *\n{}\n", rule));
+            assert!(!robots.is_allowed("SSS/1.0", "https://e.com/ข่าว/1"), "{}", rule);
+            assert!(!robots.is_allowed("SSS/1.0", "https://e.com/%E0%B8%82%E0%B9%88%E0%B8%B2%E0%B8%A7"), "{}", rule);
+            assert!(robots.is_allowed("SSS/1.0", "https://e.com/news"), "{}", rule);
+        }
+    }
+
+    #[test]
+    fn disallow_all_blocks_everything_but_robots_txt() {
+        let robots = RobotsTxt::disallow_all();
+        assert!(!robots.is_allowed("SSS/1.0", "https://e.com/"));
+        assert!(robots.is_allowed("SSS/1.0", "https://e.com/robots.txt"));
+    }
+}
@@ -0,0 +1,424 @@
+use flate2::read::MultiGzDecoder;
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use spider::url::Url;
+use std::error::Error;
+use std::io::Read;
+
+/// ขนาดสูงสุดของ sitemap หลัง decompress (50 MiB ตาม sitemaps.org) กัน zip bomb
+pub const MAX_SITEMAP_BYTES: usize = 50 * 1024 * 1024;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// หนึ่งรายการที่ใช้ค้นหา URL
+/// (`<url>`/`<sitemap>` ใน sitemap, `<item>` ใน RSS, `<entry>` ใน Atom หรือหนึ่งบรรทัดใน text sitemap)
+#[derive(Debug, Clone, Default)]
+pub struct SitemapEntry {
+    pub loc: String,
+    pub lastmod: Option<String>,
+    pub changefreq: Option<String>,
+    pub priority: Option<f32>,
+}
+
+impl SitemapEntry {
+    pub fn new(loc: &str) -> Self {
+        Self {
+            loc: loc.to_string(),
+            ..Self::default()
+        }
+    }
+}
+
+/// ผลการ parse เอกสาร discovery แยกตามชนิด
+#[derive(Debug)]
+pub enum SitemapDocument {
+    /// `<urlset>`: รายการหน้าเว็บ
+    UrlSet(Vec<SitemapEntry>),
+    /// `<sitemapindex>`: รายการ sitemap ลูกที่ต้องโหลดต่อ
+    Index(Vec<SitemapEntry>),
+    /// RSS 2.0 / RSS 1.0 (`<rss>`, `<rdf:RDF>`) หรือ Atom (`<feed>`)
+    Feed(Vec<SitemapEntry>),
+    /// text sitemap: หนึ่ง URL ต่อบรรทัด
+    Text(Vec<SitemapEntry>),
+    /// root element อื่น (ไม่ใช่ sitemap/feed)
+    Unknown(String),
+}
+
+/// parse sitemap / feed แบบ streaming
+/// - แยก `<sitemapindex>`, `<urlset>`, `<rss>`, `<feed>` จาก root element (ไม่เดาจากนามสกุลไฟล์)
+/// - ถ้าไม่ใช่ XML เลย ถือเป็น text sitemap
+/// - decode XML entities ใน `<loc>` และ field อื่น ๆ
+/// - ไม่สนใจ namespace prefix เช่น `<sm:loc>`
+/// - อ่าน field เฉพาะที่เป็นลูกโดยตรงของ entry (`<image:loc>` ใน `<image:image>` ไม่ทับ `<loc>` ของหน้า)
+pub fn parse_sitemap(content: &[u8]) -> Result<SitemapDocument, quick_xml::Error> {
+    let trimmed = content.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(content);
+    if !trimmed.trim_ascii_start().starts_with(b"<") {
+        return Ok(SitemapDocument::Text(parse_text_sitemap(trimmed)));
+    }
+
+    let mut reader = Reader::from_reader(content);
+    let mut buf = Vec::new();
+
+    let mut root: Option<String> = None;
+    let mut entries = Vec::new();
+    let mut current: Option<SitemapEntry> = None;
+    // ความลึกภายใน entry ปัจจุบัน (1 = ลูกโดยตรง)
+    let mut depth = 0usize;
+    let mut field: Option<String> = None;
+    let mut text = String::new();
+
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).to_ascii_lowercase();
+                if root.is_none() {
+                    root = Some(name);
+                } else if let Some(entry) = current.as_mut() {
+                    depth += 1;
+                    if depth == 1 {
+                        // Atom: <link href="..."></link>
+                        if name == "link" {
+                            take_atom_link(entry, &e)?;
+                        }
+                        field = Some(name);
+                        text.clear();
+                    }
+                } else if is_entry_element(&name) {
+                    current = Some(SitemapEntry::default());
+                    depth = 0;
+                }
+            }
+            // Atom: <link rel="alternate" href="..."/>
+            Event::Empty(e) if current.is_some() && depth == 0 && e.local_name().as_ref().eq_ignore_ascii_case(b"link") => {
+                if let Some(entry) = current.as_mut() {
+                    take_atom_link(entry, &e)?;
+                }
+            }
+            Event::Text(e) if field.is_some() => text.push_str(&e.decode()?),
+            Event::CData(e) if field.is_some() => text.push_str(&e.decode()?),
+            Event::GeneralRef(e) if field.is_some() => {
+                if let Some(ch) = e.resolve_char_ref()? {
+                    text.push(ch);
+                } else {
+                    let name = e.decode()?;
+                    match resolve_predefined_entity(&name) {
+                        Some(value) => text.push_str(value),
+                        None => {
+                            text.push('&');
+                            text.push_str(&name);
+                            text.push(';');
+                        }
+                    }
+                }
+            }
+            Event::End(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).to_ascii_lowercase();
+                if current.is_some() && depth == 0 {
+                    // ปิด entry
+                    if let Some(entry) = current.take().filter(|e| !e.loc.is_empty()) {
+                        entries.push(entry);
+                    }
+                } else if current.is_some() {
+                    depth -= 1;
+                }
+                if depth == 0 && field.as_deref() == Some(name.as_str()) {
+                    if let Some(entry) = current.as_mut() {
+                        let value = text.trim();
+                        match name.as_str() {
+                            "loc" => entry.loc = value.to_string(),
+                            // RSS <link> (Atom ใช้ href ซึ่งจัดการตอนเปิด tag แล้ว)
+                            "link" if entry.loc.is_empty() => entry.loc = value.to_string(),
+                            "lastmod" | "updated" | "pubdate" | "date" if !value.is_empty() => {
+                                entry.lastmod = Some(value.to_string())
+                            }
+                            "published" if entry.lastmod.is_none() && !value.is_empty() => {
+                                entry.lastmod = Some(value.to_string())
+                            }
+                            "changefreq" if !value.is_empty() => {
+                                entry.changefreq = Some(value.to_ascii_lowercase())
+                            }
+                            "priority" => {
+                                entry.priority = value.parse::<f32>().ok().map(|p| p.clamp(0.0, 1.0))
+                            }
+                            _ => {}
+                        }
+                    }
+                    field = None;
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(match root.as_deref() {
+        Some("urlset") => SitemapDocument::UrlSet(entries),
+        Some("sitemapindex") => SitemapDocument::Index(entries),
+        Some("rss") | Some("rdf") | Some("feed") => SitemapDocument::Feed(entries),
+        other => SitemapDocument::Unknown(other.unwrap_or("").to_string()),
+    })
+}
+
+fn is_entry_element(name: &str) -> bool {
+    matches!(name, "url" | "sitemap" | "item" | "entry")
+}
+
+/// ใช้ href จาก Atom `<link>` ที่ไม่มี rel หรือ rel="alternate"
+fn take_atom_link(entry: &mut SitemapEntry, e: &BytesStart) -> Result<(), quick_xml::Error> {
+    if !entry.loc.is_empty() {
+        return Ok(());
+    }
+    let rel = match e.try_get_attribute("rel")? {
+        Some(attr) => attr.unescape_value()?.to_ascii_lowercase(),
+        None => "alternate".to_string(),
+    };
+    if rel != "alternate" {
+        return Ok(());
+    }
+    if let Some(href) = e.try_get_attribute("href")? {
+        entry.loc = href.unescape_value()?.trim().to_string();
+    }
+    Ok(())
+}
+
+/// text sitemap: หนึ่ง URL (http/https) ต่อบรรทัด บรรทัดอื่นข้ามไป
+fn parse_text_sitemap(content: &[u8]) -> Vec<SitemapEntry> {
+    String::from_utf8_lossy(content)
+        .lines()
+        .map(str::trim)
+        .filter(|l| l.starts_with("http://") || l.starts_with("https://"))
+        .map(SitemapEntry::new)
+        .collect()
+}
+
+/// หา feed จาก `<link rel="alternate" type="application/rss+xml|atom+xml" href="...">` ในหน้า HTML
+/// คืน URL แบบ absolute (resolve กับ page_url)
+pub fn find_feed_links(page_url: &str, html: &str) -> Vec<String> {
+    let Ok(base) = Url::parse(page_url) else {
+        return Vec::new();
+    };
+    let lower = html.to_ascii_lowercase();
+    let mut feeds: Vec<String> = Vec::new();
+    let mut pos = 0usize;
+
+    while let Some(start_rel) = lower[pos..].find("<link") {
+        let start = pos + start_rel;
+        let Some(end_rel) = lower[start..].find('>') else {
+            break;
+        };
+        let tag = &html[start..start + end_rel + 1];
+        pos = start + end_rel + 1;
+
+        let rel = tag_attr(tag, "rel").unwrap_or_default().to_ascii_lowercase();
+        let kind = tag_attr(tag, "type").unwrap_or_default().to_ascii_lowercase();
+        if !rel.split_whitespace().any(|r| r == "alternate") {
+            continue;
+        }
+        if kind != "application/rss+xml" && kind != "application/atom+xml" {
+            continue;
+        }
+        if let Some(href) = tag_attr(tag, "href")
+            && let Ok(feed_url) = base.join(href.trim())
+            && !feeds.contains(&feed_url.to_string())
+        {
+            feeds.push(feed_url.to_string());
+        }
+    }
+    feeds
+}
+
+/// อ่านค่า attribute จาก tag HTML (รองรับ "..." , '...' และไม่มี quote)
+fn tag_attr<'a>(tag: &'a str, attr: &str) -> Option<&'a str> {
+    let lower = tag.to_ascii_lowercase();
+    let mut from = 0usize;
+    while let Some(rel) = lower[from..].find(attr) {
+        let start = from + rel;
+        from = start + attr.len();
+        // ต้องเป็นชื่อ attribute เต็ม ๆ ไม่ใช่ส่วนหนึ่งของชื่ออื่น
+        let before_ok = lower[..start].ends_with(|c: char| c.is_ascii_whitespace());
+        let rest = lower[from..].trim_start();
+        if !before_ok || !rest.starts_with('=') {
+            continue;
+        }
+        let value_start = tag.len() - rest.len() + 1;
+        let value = tag[value_start..].trim_start();
+        let offset = tag.len() - value.len();
+        return match value.chars().next() {
+            Some(q @ ('"' | '\'')) => {
+                let end = value[1..].find(q)?;
+                Some(&tag[offset + 1..offset + 1 + end])
+            }
+            Some(_) => {
+                let end = value
+                    .find(|c: char| c.is_ascii_whitespace() || c == '>' || c == '/')
+                    .unwrap_or(value.len());
+                Some(&tag[offset..offset + end])
+            }
+            None => None,
+        };
+    }
+    None
+}
+
+/// คืน body ของ sitemap ที่พร้อม parse
+/// - decompress gzip เมื่อ body ขึ้นต้นด้วย magic bytes (1f 8b)
+/// - ถ้า URL ลงท้าย .gz หรือ Content-Encoding เป็น gzip แต่ body ไม่ใช่ gzip
+///   แปลว่า HTTP client decompress ให้แล้ว ใช้ body ตามเดิม
+/// - ผลลัพธ์ใหญ่เกิน max_bytes -> error
+pub fn decode_sitemap_body(
+    url: &str,
+    body: &[u8],
+    content_encoding: Option<&str>,
+    max_bytes: usize,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let hinted = url.split(['?', '#']).next().unwrap_or(url).ends_with(".gz")
+        || content_encoding.is_some_and(|e| e.to_ascii_lowercase().contains("gzip"));
+
+    if !body.starts_with(&GZIP_MAGIC) {
+        if hinted {
+            println!("[sitemap] {} ระบุเป็น gzip แต่ body ถูก decompress แล้ว", url);
+        }
+        if body.len() > max_bytes {
+            return Err(format!("sitemap {} ใหญ่เกิน {} bytes", url, max_bytes).into());
+        }
+        return Ok(body.to_vec());
+    }
+
+    // อ่านเกิน cap มา 1 byte เพื่อรู้ว่าไฟล์จริงใหญ่กว่า cap
+    let mut decoded = Vec::new();
+    MultiGzDecoder::new(body)
+        .take(max_bytes as u64 + 1)
+        .read_to_end(&mut decoded)?;
+    if decoded.len() > max_bytes {
+        return Err(format!("sitemap {} หลัง decompress ใหญ่เกิน {} bytes", url, max_bytes).into());
+    }
+
+    println!(
+        "[sitemap] decompress gzip {}: {} -> {} bytes",
+        url,
+        body.len(),
+        decoded.len()
+    );
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urlset(xml: &str) -> Vec<SitemapEntry> {
+        match parse_sitemap(xml.as_bytes()).unwrap() {
+            SitemapDocument::UrlSet(entries) => entries,
+            other => panic!("expected urlset, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn image_extension_does_not_replace_page_loc() {
+        let entries = urlset(
+            r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9" xmlns:image="http://www.google.com/schemas/sitemap-image/1.1">
+<url><loc>https://e.com/page</loc><image:image><image:loc>https://e.com/img.jpg</image:loc></image:image><lastmod>2024-05-01</lastmod></url>
+<url><image:image><image:loc>https://e.com/first.jpg</image:loc></image:image><loc>https://e.com/other</loc></url>
+</urlset>"#,
+        );
+        let locs: Vec<&str> = entries.iter().map(|e| e.loc.as_str()).collect();
+        assert_eq!(locs, ["https://e.com/page", "https://e.com/other"]);
+        assert_eq!(entries[0].lastmod.as_deref(), Some("2024-05-01"));
+    }
+
+    #[test]
+    fn entry_fields_and_entities() {
+        let entries = urlset(
+            "<urlset><url><sm:loc>https://e.com/?a=1&amp;b=2</sm:loc><changefreq>Daily</changefreq><priority>1.5</priority></url></urlset>",
+        );
+        assert_eq!(entries[0].loc, "https://e.com/?a=1&b=2");
+        assert_eq!(entries[0].changefreq.as_deref(), Some("daily"));
+        assert_eq!(entries[0].priority, Some(1.0));
+    }
+
+    #[test]
+    fn atom_feed_uses_alternate_link() {
+        let xml = r#"<feed xmlns="http://www.w3.org/2005/Atom"><link href="https://e.com/"/>
+<entry><link rel="self" href="https://e.com/self"/><link href="https://e.com/post"/><updated>2024-01-01T00:00:00Z</updated></entry></feed>"#;
+        let SitemapDocument::Feed(entries) = parse_sitemap(xml.as_bytes()).unwrap() else {
+            panic!("expected feed");
+        };
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].loc, "https://e.com/post");
+        assert_eq!(entries[0].lastmod.as_deref(), Some("2024-01-01T00:00:00Z"));
+    }
+
+    #[test]
+    fn rss_item_link_and_pub_date() {
+        let xml = r#"<?xml version="1.0"?><rss version="2.0"><channel><link>https://e.com/</link>
+<item><title>A</title><link> https://e.com/a </link><pubDate>Mon, 01 Jan 2024 00:00:00 GMT</pubDate></item>
+<item><guid>https://e.com/b</guid><link>https://e.com/b</link></item></channel></rss>"#;
+        let SitemapDocument::Feed(entries) = parse_sitemap(xml.as_bytes()).unwrap() else {
+            panic!("expected feed");
+        };
+        let locs: Vec<&str> = entries.iter().map(|e| e.loc.as_str()).collect();
+        assert_eq!(locs, ["https://e.com/a", "https://e.com/b"]);
+        assert_eq!(entries[0].lastmod.as_deref(), Some("Mon, 01 Jan 2024 00:00:00 GMT"));
+        assert_eq!(entries[1].lastmod, None);
+    }
+
+    #[test]
+    fn text_sitemap_skips_blank_and_comment_lines() {
+        let entries = parse_text_sitemap(b"# comment\n\nhttps://e.com/a\r\n  http://e.com/b  \n/relative\nftp://e.com/c\n");
+        let locs: Vec<&str> = entries.iter().map(|e| e.loc.as_str()).collect();
+        assert_eq!(locs, ["https://e.com/a", "http://e.com/b"]);
+    }
+
+    #[test]
+    fn feed_links_resolve_relative_hrefs_and_filter_types() {
+        let html = r#"<html><head>
+<LINK REL="alternate" TYPE="application/rss+xml" HREF="/feed.xml">
+<link rel='alternate' type='application/atom+xml' href='atom.xml'>
+<link rel="alternate" type="text/html" href="/other">
+<link rel="stylesheet" type="application/rss+xml" href="/not-a-feed">
+<link rel="alternate" type="application/rss+xml" href="https://e.com/feed.xml">
+</head></html>"#;
+        assert_eq!(
+            find_feed_links("https://e.com/blog/", html),
+            ["https://e.com/feed.xml", "https://e.com/blog/atom.xml"]
+        );
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn gzip_body_is_detected_by_magic_bytes() {
+        let xml = b"<urlset><url><loc>https://e.com/a</loc></url></urlset>";
+        // ไม่มี .gz หรือ Content-Encoding ก็ decompress จาก magic bytes
+        let decoded = decode_sitemap_body("https://e.com/sitemap.xml", &gzip(xml), None, MAX_SITEMAP_BYTES).unwrap();
+        assert_eq!(decoded, xml);
+        // URL บอกว่า .gz แต่ client decompress ให้แล้ว
+        let decoded = decode_sitemap_body("https://e.com/sitemap.xml.gz?v=1", xml, Some("gzip"), MAX_SITEMAP_BYTES).unwrap();
+        assert_eq!(decoded, xml);
+        assert_eq!(urlset(std::str::from_utf8(&decoded).unwrap())[0].loc, "https://e.com/a");
+    }
+
+    #[test]
+    fn multi_member_gzip_is_concatenated() {
+        let body = [gzip(b"<urlset><url><loc>https://e.com/a</loc></url>"), gzip(b"</urlset>")].concat();
+        let decoded = decode_sitemap_body("https://e.com/sitemap.xml.gz", &body, None, MAX_SITEMAP_BYTES).unwrap();
+        assert_eq!(decoded, b"<urlset><url><loc>https://e.com/a</loc></url></urlset>");
+    }
+
+    #[test]
+    fn body_over_cap_is_rejected() {
+        let xml = b"<urlset>0123456789</urlset>";
+        assert!(decode_sitemap_body("https://e.com/s.xml.gz", &gzip(xml), None, xml.len()).is_ok());
+        assert!(decode_sitemap_body("https://e.com/s.xml.gz", &gzip(xml), None, xml.len() - 1).is_err());
+        assert!(decode_sitemap_body("https://e.com/s.xml", xml, None, xml.len() - 1).is_err());
+    }
+}
@@ -0,0 +1,242 @@
+use chrono::{DateTime, NaiveDate};
+use spider::url::Url;
+use std::collections::{HashMap, VecDeque};
+
+use super::sitemap::SitemapEntry;
+
+/// วิธีเลือก URL จาก sitemap เมื่อจำนวนเกิน max_sitemap_urls
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelectionStrategy {
+    /// ตามลำดับในไฟล์ sitemap (พฤติกรรมเดิม)
+    SitemapOrder,
+    /// lastmod ใหม่สุดก่อน
+    NewestFirst,
+    /// priority สูงสุดก่อน
+    PriorityFirst,
+    /// path ตื้นสุดก่อน เช่น /about ก่อน /blog/2024/post
+    ShallowestFirst,
+    /// เฉลี่ยโควตาให้ทุก section (path segment แรก) แบบ round-robin
+    Stratified,
+}
+
+impl SelectionStrategy {
+    pub fn from_name(s: &str) -> Self {
+        match s {
+            "newest_first" => SelectionStrategy::NewestFirst,
+            "priority_first" => SelectionStrategy::PriorityFirst,
+            "shallowest_first" => SelectionStrategy::ShallowestFirst,
+            "stratified" => SelectionStrategy::Stratified,
+            _ => SelectionStrategy::SitemapOrder,
+        }
+    }
+}
+
+/// priority ที่ไม่ได้ระบุมีค่า default 0.5 ตาม sitemaps.org
+const DEFAULT_PRIORITY: f32 = 0.5;
+
+/// เรียงและตัด entries ให้เหลือ limit รายการตาม strategy
+/// ทุก strategy เป็น stable sort: ค่าที่เท่ากันยังคงลำดับเดิมใน sitemap
+pub fn select_entries(
+    mut entries: Vec<SitemapEntry>,
+    strategy: SelectionStrategy,
+    limit: usize,
+) -> Vec<SitemapEntry> {
+    match strategy {
+        SelectionStrategy::SitemapOrder => {}
+        SelectionStrategy::NewestFirst => {
+            // entry ที่ไม่มี lastmod ไปอยู่ท้ายสุด
+            entries.sort_by_key(|e| std::cmp::Reverse(e.lastmod.as_deref().and_then(parse_lastmod)));
+        }
+        SelectionStrategy::PriorityFirst => {
+            entries.sort_by(|a, b| {
+                let pa = a.priority.unwrap_or(DEFAULT_PRIORITY);
+                let pb = b.priority.unwrap_or(DEFAULT_PRIORITY);
+                pb.total_cmp(&pa)
+            });
+        }
+        SelectionStrategy::ShallowestFirst => {
+            entries.sort_by_key(|e| path_depth(&e.loc));
+        }
+        SelectionStrategy::Stratified => {
+            entries = stratify(entries, limit);
+        }
+    }
+    entries.truncate(limit);
+    entries
+}
+
+/// แปลง lastmod (W3C Datetime ของ sitemap หรือ RFC 2822 ของ RSS) เป็น unix timestamp
+pub fn parse_lastmod(value: &str) -> Option<i64> {
+    let value = value.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Some(dt.timestamp());
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc2822(value) {
+        return Some(dt.timestamp());
+    }
+    // W3C อนุญาตให้ไม่มีวินาที เช่น 2024-01-02T10:00+07:00
+    for fmt in ["%Y-%m-%dT%H:%M%:z", "%Y-%m-%dT%H:%M%#z"] {
+        if let Ok(dt) = DateTime::parse_from_str(value, fmt) {
+            return Some(dt.timestamp());
+        }
+    }
+    if let Some(stripped) = value.strip_suffix('Z')
+        && let Ok(dt) = DateTime::parse_from_str(&format!("{}+00:00", stripped), "%Y-%m-%dT%H:%M%:z")
+    {
+        return Some(dt.timestamp());
+    }
+    // YYYY-MM-DD, YYYY-MM, YYYY
+    let date = match value.len() {
+        10 => NaiveDate::parse_from_str(value, "%Y-%m-%d").ok(),
+        7 => NaiveDate::parse_from_str(&format!("{}-01", value), "%Y-%m-%d").ok(),
+        4 => NaiveDate::parse_from_str(&format!("{}-01-01", value), "%Y-%m-%d").ok(),
+        _ => None,
+    }?;
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp())
+}
+
+fn path_depth(url: &str) -> usize {
+    Url::parse(url)
+        .ok()
+        .and_then(|u| u.path_segments().map(|s| s.filter(|seg| !seg.is_empty()).count()))
+        .unwrap_or(usize::MAX)
+}
+
+/// section ของ URL = host + path segment แรก (หน้าแรกของแต่ละ host เป็น section ของตัวเอง)
+fn section_of(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(u) => {
+            let first = u
+                .path_segments()
+                .and_then(|mut s| s.find(|seg| !seg.is_empty()).map(str::to_string))
+                .unwrap_or_default();
+            format!("{}/{}", u.host_str().unwrap_or(""), first)
+        }
+        Err(_) => String::new(),
+    }
+}
+
+/// เลือกแบบ round-robin ทีละ section ตามลำดับที่ section ปรากฏครั้งแรก
+fn stratify(entries: Vec<SitemapEntry>, limit: usize) -> Vec<SitemapEntry> {
+    let mut order: Vec<String> = Vec::new();
+    let mut buckets: HashMap<String, VecDeque<SitemapEntry>> = HashMap::new();
+    for entry in entries {
+        let section = section_of(&entry.loc);
+        if !buckets.contains_key(&section) {
+            order.push(section.clone());
+        }
+        buckets.entry(section).or_default().push_back(entry);
+    }
+
+    let mut selected = Vec::new();
+    while selected.len() < limit {
+        let mut took_any = false;
+        for section in &order {
+            if selected.len() >= limit {
+                break;
+            }
+            if let Some(entry) = buckets.get_mut(section).and_then(|b| b.pop_front()) {
+                selected.push(entry);
+                took_any = true;
+            }
+        }
+        if !took_any {
+            break;
+        }
+    }
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(loc: &str, lastmod: Option<&str>, priority: Option<f32>) -> SitemapEntry {
+        SitemapEntry {
+            loc: format!("https://example.com{}", loc),
+            lastmod: lastmod.map(str::to_string),
+            priority,
+            ..SitemapEntry::default()
+        }
+    }
+
+    fn paths(entries: &[SitemapEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.loc.trim_start_matches("https://example.com")).collect()
+    }
+
+    fn sample() -> Vec<SitemapEntry> {
+        vec![
+            entry("/blog/2024/old", Some("2023-01-01"), Some(0.3)),
+            entry("/blog/new", Some("2024-06-01T08:00:00+07:00"), None),
+            entry("/docs/a/b/c", None, Some(0.9)),
+            entry("/about", Some("2024-06-01"), Some(0.9)),
+            entry("/", None, None),
+        ]
+    }
+
+    #[test]
+    fn each_strategy_orders_then_truncates() {
+        let cases = [
+            (SelectionStrategy::SitemapOrder, vec!["/blog/2024/old", "/blog/new", "/docs/a/b/c"]),
+            // /blog/new = 2024-06-01T01:00Z ใหม่กว่า /about (date-only = 00:00Z), ไม่มี lastmod อยู่ท้าย
+            (SelectionStrategy::NewestFirst, vec!["/blog/new", "/about", "/blog/2024/old"]),
+            // priority เท่ากันคงลำดับเดิม, ไม่ระบุ = 0.5
+            (SelectionStrategy::PriorityFirst, vec!["/docs/a/b/c", "/about", "/blog/new"]),
+            (SelectionStrategy::ShallowestFirst, vec!["/", "/about", "/blog/new"]),
+            (SelectionStrategy::Stratified, vec!["/blog/2024/old", "/docs/a/b/c", "/about"]),
+        ];
+        for (strategy, expected) in cases {
+            assert_eq!(paths(&select_entries(sample(), strategy, 3)), expected, "{:?}", strategy);
+        }
+    }
+
+    #[test]
+    fn entries_without_lastmod_or_priority_keep_sitemap_order() {
+        let entries = vec![entry("/c", None, None), entry("/a", None, None), entry("/b", Some("bogus"), None)];
+        for strategy in [SelectionStrategy::NewestFirst, SelectionStrategy::PriorityFirst] {
+            assert_eq!(paths(&select_entries(entries.clone(), strategy, 10)), ["/c", "/a", "/b"], "{:?}", strategy);
+        }
+    }
+
+    #[test]
+    fn parse_lastmod_formats() {
+        let midnight_utc = 1_704_153_600; // 2024-01-02T00:00:00Z
+        let cases = [
+            ("2024-01-02", Some(midnight_utc)),
+            (" 2024-01-02T00:00:00Z ", Some(midnight_utc)),
+            ("2024-01-02T07:00:00+07:00", Some(midnight_utc)),
+            ("2024-01-02T07:00+07:00", Some(midnight_utc)),
+            ("2024-01-02T00:00Z", Some(midnight_utc)),
+            ("Tue, 02 Jan 2024 00:00:00 GMT", Some(midnight_utc)),
+            ("2024-01", Some(1_704_067_200)),
+            ("2024", Some(1_704_067_200)),
+            ("2024-13-01", None),
+            ("yesterday", None),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(parse_lastmod(value), expected, "{}", value);
+        }
+    }
+
+    #[test]
+    fn stratify_round_robins_sections_in_first_seen_order() {
+        let entries = vec![
+            entry("/blog/1", None, None),
+            entry("/blog/2", None, None),
+            entry("/blog/3", None, None),
+            entry("/blog/4", None, None),
+            entry("/docs/1", None, None),
+            entry("/news/1", None, None),
+            entry("/news/2", None, None),
+        ];
+        assert_eq!(
+            paths(&select_entries(entries.clone(), SelectionStrategy::Stratified, 5)),
+            ["/blog/1", "/docs/1", "/news/1", "/blog/2", "/news/2"]
+        );
+        // section ที่หมดแล้วถูกข้าม ส่วนที่เหลือยังได้ครบจนถึง limit
+        assert_eq!(
+            paths(&select_entries(entries, SelectionStrategy::Stratified, 100)),
+            ["/blog/1", "/docs/1", "/news/1", "/blog/2", "/news/2", "/blog/3", "/blog/4"]
+        );
+    }
+}